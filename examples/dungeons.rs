@@ -21,6 +21,7 @@ pub async fn main() {
         // We might have dungeon keys still waiting to be unlocked, so we
         // should use everything we have
         if let Some(unlockable) = gs.pending_unlocks.first().copied() {
+            println!("Unlocking: {}", unlockable.describe());
             session
                 .send_command(Command::UnlockFeature { unlockable })
                 .await
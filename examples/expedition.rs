@@ -3,7 +3,7 @@ use std::{borrow::Borrow, time::Duration};
 use chrono::{DateTime, Local};
 use sf_api::{
     command::{Command, ExpeditionSetting, TimeSkip},
-    gamestate::tavern::{AvailableTasks, ExpeditionStage},
+    gamestate::tavern::{AvailableTasks, ExpeditionStage, ExpeditionValue},
     session::SimpleSession,
 };
 use tokio::time::sleep;
@@ -109,9 +109,20 @@ pub async fn main() {
                 if roads.is_empty() {
                     panic!("No crossroads to choose from");
                 }
-                // We should pick the best crossroad here
+                // This is a very simple heuristic. Things, that directly give
+                // heroism are preferred, after that the heroism decides
+                let pos = roads
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, road)| {
+                        let direct =
+                            road.typ.value_hint() == ExpeditionValue::Heroism;
+                        (direct, road.heroism)
+                    })
+                    .map(|(pos, _)| pos)
+                    .unwrap_or(0);
                 println!("Choosing crossroad");
-                Command::ExpeditionPickEncounter { pos: 0 }
+                Command::ExpeditionPickEncounter { pos }
             }
             ExpeditionStage::Finished => {
                 // Between calling current_stage and now the expedition
@@ -5,8 +5,8 @@ use chrono::{DateTime, Local};
 use sf_api::{
     command::{Command, ExpeditionSetting, TimeSkip},
     gamestate::{
-        items::{Enchantment, EquipmentSlot},
-        tavern::{AvailableTasks, CurrentAction},
+        items::EquipmentSlot,
+        tavern::{AvailableTasks, CurrentAction, QuestPriority},
     },
     misc::EnumMapGet,
     session::SimpleSession,
@@ -24,22 +24,16 @@ pub async fn main() {
         match &gs.tavern.current_action {
             CurrentAction::Idle => match gs.tavern.available_tasks() {
                 AvailableTasks::Quests(q) => {
-                    // You should pick the best quest here, but this is an
-                    // example
-                    let best_quest = q.first().unwrap();
+                    let quest_pos = gs
+                        .tavern
+                        .best_quest(QuestPriority::Experience)
+                        .unwrap_or(0);
+                    let best_quest = q.get(quest_pos).unwrap();
 
                     if best_quest.base_length
                         > gs.tavern.thirst_for_adventure_sec
                     {
-                        let has_extra_beer = gs
-                            .character
-                            .equipment
-                            .has_enchantment(Enchantment::ThirstyWanderer);
-
-                        if gs.character.mushrooms > 0
-                            && gs.tavern.beer_drunk
-                                < (10 + has_extra_beer as u8)
-                        {
+                        if gs.tavern.can_buy_beer(&gs.character) {
                             println!("Buying beer");
                             session
                                 .send_command(Command::BuyBeer)
@@ -68,7 +62,7 @@ pub async fn main() {
 
                     session
                         .send_command(Command::StartQuest {
-                            quest_pos: 0,
+                            quest_pos,
                             overwrite_inv: true,
                         })
                         .await
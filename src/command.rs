@@ -263,7 +263,8 @@ pub enum Command {
         inventory_pos: usize,
     },
     /// Sells an item from the players inventory. To make this more convenient,
-    /// this picks a shop&item position to sell to for you
+    /// this picks a random shop&item position to sell to for you. Because of
+    /// that, `request_string()` can differ between calls for this command
     SellShop {
         /// The inventory you want to sell an item from
         inventory: PlayerItemPlace,
@@ -373,7 +374,8 @@ pub enum Command {
         player_pet_id: u32,
     },
     /// Sets the guild info. Note the info about length limit from
-    /// `SetDescription` for the description
+    /// `SetDescription` for the description. The limit for guild descriptions
+    /// is `MAX_GUILD_DESCRIPTION_LEN`
     GuildSetInfo {
         /// The description you want to set
         description: String,
@@ -394,7 +396,9 @@ pub enum Command {
         /// The amount of mushrooms to gamble
         amount: u64,
     },
-    /// Sends a message to another player
+    /// Sends a message to another player. The server limits the length of
+    /// the message after escaping, but the exact limit is not known yet. See
+    /// `SetDescription` for more information on the escaping
     SendMessage {
         /// The name of the player to send a message to
         to: String,
@@ -408,7 +412,8 @@ pub enum Command {
     /// amount of escaped chars. We 'could' truncate the response, but
     /// that could get weird with character boundaries in UTF8 and split the
     /// escapes themself, so just make sure you provide a valid value here
-    /// to begin with and be prepared for a server error
+    /// to begin with. You can use `validate()` to check this before sending
+    /// the command. See `MAX_DESCRIPTION_LEN`
     SetDescription {
         /// The description to set
         description: String,
@@ -460,7 +465,8 @@ pub enum Command {
     EquipCompanion {
         /// The inventory of your character you take the item from
         from_inventory: InventoryType,
-        /// The position in the inventory, that you
+        /// The position in the inventory, that you want to take the item from.
+        /// Like all other positions, this starts at 0
         from_pos: u8,
         /// The companion you want to equip
         to_companion: CompanionClass,
@@ -531,7 +537,9 @@ pub enum Command {
     },
     /// Upgrades the Hall of Knights to the next level
     FortressUpgradeHallOfKnights,
-    /// Sends a whisper message to another player
+    /// Sends a whisper message to another player. The server limits the
+    /// length of the message after escaping, but the exact limit is not known
+    /// yet
     Whisper {
         player_name: String,
         message: String,
@@ -661,8 +669,9 @@ pub enum Command {
     },
     /// Changes the look of the item to the selected `raw_model_id` for 10
     /// mushrooms. Note that this is NOT the normal model id. it is the
-    /// `model_id + (class as usize) * 1000` if I remember correctly. Pretty
-    /// sure nobody will ever uses this though, as it is only for looks.
+    /// `model_id + (class as usize) * 1000` if I remember correctly. You can
+    /// use `raw_look_id()` on the item to calculate this. Pretty sure nobody
+    /// will ever uses this though, as it is only for looks.
     ChangeItemLook {
         inv: ItemPlace,
         pos: usize,
@@ -829,6 +838,18 @@ pub enum ShopType {
     Magic = 4,
 }
 
+impl ShopType {
+    /// `ShopType` is a subset of `ItemPlace`. This is a convenient function to
+    /// convert between them
+    #[must_use]
+    pub fn item_position(&self) -> ItemPlace {
+        match self {
+            ShopType::Weapon => ItemPlace::WeaponShop,
+            ShopType::Magic => ItemPlace::MageShop,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
@@ -838,14 +859,105 @@ pub enum TimeSkip {
     Glass = 2,
 }
 
+/// The maximum length the character description can have, when it reaches
+/// the server. Note that this is the length after escaping special chars
+pub const MAX_DESCRIPTION_LEN: usize = 240;
+/// The maximum length the guild description can have, when it reaches the
+/// server. The game uses the same limit as for character descriptions
+pub const MAX_GUILD_DESCRIPTION_LEN: usize = 240;
+/// The length, above which a message, or whisper to another player is likely
+/// too long for the server. This is provisional, as the server limit for
+/// messages has not been confirmed yet, so `validate()` only warns about it
+const PROVISIONAL_MESSAGE_LEN: usize = 240;
+
 impl Command {
+    /// Checks the command for values, that we know the server will reject.
+    /// Currently this makes sure, that descriptions are not too long after
+    /// escaping them. Messages and whispers, that look too long, only log a
+    /// warning, as their limit is not confirmed. This is not done
+    /// automatically when sending a command, so call this yourself, if you
+    /// want to catch these errors before they reach the server
+    ///
+    /// # Errors
+    /// Returns an `InvalidRequest` error, if the command is known to be
+    /// invalid
+    pub fn validate(&self) -> Result<(), crate::error::SFError> {
+        use crate::{error::SFError, misc::to_sf_string};
+
+        let escaped_len = |text: &str| to_sf_string(text).chars().count();
+
+        let (text, max_len, error) = match self {
+            Command::SendMessage { msg: text, .. }
+            | Command::Whisper { message: text, .. } => {
+                if escaped_len(text) > PROVISIONAL_MESSAGE_LEN {
+                    warn!(
+                        "The message is longer than {PROVISIONAL_MESSAGE_LEN} \
+                         chars after escaping and might get rejected"
+                    );
+                }
+                return Ok(());
+            }
+            Command::SetDescription { description } => (
+                description,
+                MAX_DESCRIPTION_LEN,
+                "The description is too long after escaping",
+            ),
+            Command::GuildSetInfo { description, .. } => (
+                description,
+                MAX_GUILD_DESCRIPTION_LEN,
+                "The guild description is too long after escaping",
+            ),
+            _ => return Ok(()),
+        };
+
+        if escaped_len(text) > max_len {
+            return Err(SFError::InvalidRequest(error));
+        }
+        Ok(())
+    }
+
+    /// Checks if sending this command multiple times in a row has the same
+    /// effect as sending it once. These are basically all the commands, that
+    /// only fetch information, so a command queue can safely drop duplicates
+    /// of them. Anything, that costs something, or changes the character is
+    /// not idempotent
+    #[must_use]
+    pub fn is_idempotent(&self) -> bool {
+        matches!(
+            self,
+            Command::Update
+                | Command::HallOfFamePage { .. }
+                | Command::HallOfFameFortressPage { .. }
+                | Command::HallOfFameGroupPage { .. }
+                | Command::HallOfFameUnderworldPage { .. }
+                | Command::HallOfFamePetsPage { .. }
+                | Command::HallOfFameHellevatorPage { .. }
+                | Command::ViewPlayer { .. }
+                | Command::CheckNameAvailable { .. }
+                | Command::CheckArena
+                | Command::ViewGuild { .. }
+                | Command::GuildGetFightableTargets
+                | Command::MessageOpen { .. }
+                | Command::ViewScrapbook
+                | Command::ViewPet { .. }
+                | Command::HellevatorViewGuildRanking
+                | Command::HellevatorPreviewRewards
+                | Command::ClaimablePreview { .. }
+        )
+    }
+
     /// Returns the unencrypted string, that has to be send to the server to to
-    /// perform the request
+    /// perform the request. This does not need a session, so you can also use
+    /// this to log, or inspect what a command would send. The same command
+    /// always results in the same string, except for `Command::SellShop`,
+    /// which picks a new random shop slot every time this is called. Use
+    /// `GameState::sell_command()`, if you need a stable preview
+    ///
+    /// # Errors
+    /// Returns an `InvalidRequest` error, if the command contains values, that
+    /// can not be send. Note that this does not call `validate()`
     #[allow(deprecated, clippy::useless_format)]
-    #[cfg(feature = "session")]
-    pub(crate) fn request_string(
-        &self,
-    ) -> Result<String, crate::error::SFError> {
+    pub fn request_string(&self) -> Result<String, crate::error::SFError> {
         const APP_VERSION: &str = "2100000000000";
         use crate::{
             error::SFError,
@@ -891,7 +1003,7 @@ impl Command {
                      {}/{}/{}/8,203,201,6,199,3,1,2,1/0//en",
                     *gender as usize + 1,
                     *race as usize,
-                    *class as usize + 1
+                    class.registration_id()
                 )
             }
             Command::Update => "Poll:".to_string(),
@@ -1160,7 +1272,7 @@ impl Command {
             } => format!(
                 "PlayerItemMove:{}/{}/{}/{}",
                 *from_inventory as usize,
-                *from_pos,
+                *from_pos + 1,
                 *to_companion as u8 + 101,
                 *to_slot as usize
             ),
@@ -1262,7 +1374,7 @@ impl Command {
             Command::SwapManequin => format!("PlayerDummySwap:301/1"),
             Command::UpdateFlag { flag } => format!(
                 "PlayerSetFlag:{}",
-                flag.map(Flag::code).unwrap_or_default()
+                flag.map(Flag::to_code).unwrap_or_default()
             ),
             Command::BlockGuildInvites { block_invites } => {
                 format!("PlayerSetNoGroupInvite:{}", u8::from(*block_invites))
@@ -1472,7 +1584,10 @@ macro_rules! generate_flag_enum {
         }
 
         impl Flag {
-            pub(crate) fn code(self) -> &'static str {
+            /// The ISO 3166-1 alpha-2 country code of this flag in lower
+            /// case, like "de" for Germany
+            #[must_use]
+            pub fn to_code(self) -> &'static str {
                 match self {
                     $(
                         Flag::$variant => $code,
@@ -1480,22 +1595,30 @@ macro_rules! generate_flag_enum {
                 }
             }
 
-            pub(crate) fn parse(value: &str) -> Option<Self> {
-                if value.is_empty() {
-                    return None;
-                }
-
+            /// Converts a lower case ISO 3166-1 alpha-2 country code, like
+            /// "de" into the flag of that country. If there is no flag for
+            /// this code, `None` is returned. All available flags can be
+            /// iterated with `Flag::iter()`
+            #[must_use]
+            pub fn from_code(code: &str) -> Option<Self> {
                 // Mapping from string codes to enum variants
-                match value {
+                match code {
                     $(
                         $code => Some(Flag::$variant),
                     )*
+                    _ => None,
+                }
+            }
 
-                    _ => {
-                        warn!("Invalid flag value: {value}");
-                        None
-                    }
+            pub(crate) fn parse(value: &str) -> Option<Self> {
+                if value.is_empty() {
+                    return None;
+                }
+                let flag = Self::from_code(value);
+                if flag.is_none() {
+                    warn!("Invalid flag value: {value}");
                 }
+                flag
             }
         }
     };
@@ -1557,3 +1680,148 @@ generate_flag_enum! {
     Venezuela => "ve",
     Vietnam => "vn"
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn description(text: String) -> Command {
+        Command::SetDescription { description: text }
+    }
+
+    #[test]
+    fn description_len_limit() {
+        let at_limit = "a".repeat(MAX_DESCRIPTION_LEN);
+        assert!(description(at_limit.clone()).validate().is_ok());
+        assert!(description(at_limit + "a").validate().is_err());
+    }
+
+    #[test]
+    fn escaped_chars_count_twice() {
+        // Each '/' is send as "$s"
+        let at_limit = "/".repeat(MAX_DESCRIPTION_LEN / 2);
+        assert!(description(at_limit.clone()).validate().is_ok());
+        assert!(description(at_limit + "a").validate().is_err());
+    }
+
+    #[test]
+    fn multibyte_chars_count_once() {
+        let at_limit = "ä".repeat(MAX_DESCRIPTION_LEN);
+        assert!(at_limit.len() > MAX_DESCRIPTION_LEN);
+        assert!(description(at_limit.clone()).validate().is_ok());
+        assert!(description(at_limit + "🦀").validate().is_err());
+
+        let at_limit = "€/".repeat(MAX_DESCRIPTION_LEN / 3);
+        assert!(description(at_limit.clone()).validate().is_ok());
+        assert!(description(at_limit + "ü").validate().is_err());
+    }
+
+    #[test]
+    fn long_messages_only_warn() {
+        let long = "ä/".repeat(PROVISIONAL_MESSAGE_LEN);
+        let message = Command::SendMessage {
+            to: "player".to_string(),
+            msg: long.clone(),
+        };
+        assert!(message.validate().is_ok());
+        let whisper = Command::Whisper {
+            player_name: "player".to_string(),
+            message: long,
+        };
+        assert!(whisper.validate().is_ok());
+    }
+
+    #[test]
+    fn guild_description_len_limit() {
+        let info = |description: String| Command::GuildSetInfo {
+            description,
+            emblem: Emblem::default(),
+        };
+        let at_limit = "b".repeat(MAX_GUILD_DESCRIPTION_LEN);
+        assert!(info(at_limit.clone()).validate().is_ok());
+        assert!(info(at_limit + "b").validate().is_err());
+    }
+
+    #[test]
+    fn request_string_does_not_validate() {
+        let long = description("a".repeat(MAX_DESCRIPTION_LEN + 1));
+        assert!(long.request_string().is_ok());
+    }
+
+    #[test]
+    fn equip_companion_uses_one_based_positions() {
+        let equip = Command::EquipCompanion {
+            from_inventory: InventoryType::MainInventory,
+            from_pos: 0,
+            to_companion: CompanionClass::Mage,
+            to_slot: EquipmentSlot::Hat,
+        };
+        assert_eq!(
+            equip.request_string().ok().as_deref(),
+            Some("PlayerItemMove:2/1/102/1")
+        );
+        // The first bag slot has to be send the same way, as when moving the
+        // item anywhere else
+        let item_move = Command::ItemMove {
+            from: ItemPlace::MainInventory,
+            from_pos: 0,
+            to: ItemPlace::Equipment,
+            to_pos: 0,
+        };
+        assert_eq!(
+            item_move.request_string().ok().as_deref(),
+            Some("PlayerItemMove:2/1/1/1")
+        );
+    }
+
+    #[test]
+    fn request_strings_are_stable() {
+        let commands = [
+            Command::Update,
+            Command::HallOfFamePage { page: 3 },
+            description("a/b:c".to_string()),
+            Command::ItemMove {
+                from: ItemPlace::MainInventory,
+                from_pos: 2,
+                to: ItemPlace::FortressChest,
+                to_pos: 0,
+            },
+        ];
+        for command in commands {
+            let first = command.request_string().ok();
+            assert!(first.is_some());
+            assert_eq!(first, command.request_string().ok());
+        }
+    }
+
+    #[test]
+    fn only_lookups_are_idempotent() {
+        let idempotent = [
+            Command::Update,
+            Command::HallOfFamePage { page: 2 },
+            Command::ViewPlayer {
+                ident: "player".to_string(),
+            },
+            Command::ViewGuild {
+                guild_ident: "guild".to_string(),
+            },
+            Command::CheckArena,
+        ];
+        for command in idempotent {
+            assert!(command.is_idempotent(), "{command:?}");
+        }
+
+        let changing = [
+            Command::BuyBeer,
+            Command::StartQuest {
+                quest_pos: 0,
+                overwrite_inv: false,
+            },
+            Command::MessageDelete { pos: -1 },
+            description("text".to_string()),
+        ];
+        for command in changing {
+            assert!(!command.is_idempotent(), "{command:?}");
+        }
+    }
+}
@@ -4,6 +4,7 @@ use chrono::{DateTime, Local};
 use enum_map::EnumMap;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
+use strum::{EnumIter, IntoEnumIterator};
 
 use super::{Mirror, NormalCost, RelationEntry, SFError, ScrapBook};
 use crate::{command::*, gamestate::items::*, misc::*, PlayerId};
@@ -94,6 +95,241 @@ pub struct Character {
     pub relations: Vec<RelationEntry>,
 }
 
+impl Character {
+    /// Checks if the character has at least the given amount of mushrooms
+    #[must_use]
+    pub fn can_afford_mushrooms(&self, amount: u32) -> bool {
+        self.mushrooms >= amount
+    }
+
+    /// Plans how to spend the mushrooms of this character on the given
+    /// requests. The budget is the most you want to spend and gets limited to
+    /// the mushrooms the character actually has. See `SpendPlan::new()` for
+    /// how the requests are prioritized
+    #[must_use]
+    pub fn spend_plan(
+        &self,
+        budget: u32,
+        requests: &[SpendRequest],
+    ) -> SpendPlan {
+        SpendPlan::new(budget.min(self.mushrooms), requests)
+    }
+
+    /// The silver and mushrooms this character currently has
+    #[must_use]
+    pub fn wallet(&self) -> Wallet {
+        Wallet {
+            silver: self.silver,
+            mushrooms: self.mushrooms,
+        }
+    }
+
+    /// Checks if the character has enough silver and mushrooms to buy the
+    /// given mount via `Command::BuyMount`
+    #[must_use]
+    pub fn can_afford_mount(&self, mount: Mount) -> bool {
+        self.wallet().can_afford(mount.cost())
+    }
+
+    /// Creates the command to change the description of this character to
+    /// `new`. See `Command::SetDescription` for the length limit
+    ///
+    /// # Errors
+    /// Returns an `InvalidRequest` error, if the description is longer than
+    /// `MAX_DESCRIPTION_LEN` after escaping
+    #[allow(clippy::unused_self)]
+    pub fn set_description_command(
+        &self,
+        new: &str,
+    ) -> Result<Command, SFError> {
+        let command = Command::SetDescription {
+            description: new.to_string(),
+        };
+        command.validate()?;
+        Ok(command)
+    }
+
+    /// Returns the active potions, that have not yet expired at `now`.
+    /// Potions with an unknown expiry time are assumed to still be active
+    #[must_use]
+    pub fn effective_potions(
+        &self,
+        now: DateTime<Local>,
+    ) -> [Option<Potion>; 3] {
+        self.active_potions
+            .map(|a| a.filter(|p| p.expires.is_none_or(|e| e > now)))
+    }
+
+    /// Calculates the change in attributes, that swapping the currently
+    /// equipped items with the ones in the manequin via
+    /// `Command::SwapManequin` would cause. Positive values mean the attribute
+    /// would increase. Returns `None`, if there is no manequin
+    #[must_use]
+    pub fn manequin_swap_preview(&self) -> Option<AttributeDelta> {
+        let manequin = self.manequin.as_ref()?.attributes(self.class, false);
+        let current = self.equipment.attributes(self.class, false);
+        let mut res = AttributeDelta::default();
+        for (typ, delta) in &mut res {
+            *delta =
+                i64::from(*manequin.get(typ)) - i64::from(*current.get(typ));
+        }
+        Some(res)
+    }
+
+    /// The attributes the character has bought/leveled, without anything from
+    /// equipment, pets, potions, etc. These are the values, that
+    /// `Command::IncreaseAttribute` increases
+    #[must_use]
+    pub fn base_attributes(&self) -> &EnumMap<AttributeType, u32> {
+        &self.attribute_basis
+    }
+
+    /// The total attributes of the character, as shown in the character
+    /// screen. This is the base attributes plus all the bonus attributes from
+    /// equipment, pets, potions, etc. as calculated by the server
+    #[must_use]
+    pub fn total_attributes(&self) -> EnumMap<AttributeType, u32> {
+        let mut total = self.attribute_basis;
+        for (typ, val) in &mut total {
+            *val += self.attribute_additions.get(typ);
+        }
+        total
+    }
+
+    /// Returns the best (fastest) mount, that the character can currently
+    /// afford. Mounts, that cost mushrooms are only considered, if
+    /// `use_mushrooms` is true
+    #[must_use]
+    pub fn recommended_mount(&self, use_mushrooms: bool) -> Option<Mount> {
+        [Mount::Dragon, Mount::Tiger, Mount::Horse, Mount::Cow]
+            .into_iter()
+            .filter(|a| use_mushrooms || a.cost().mushrooms == 0)
+            .find(|a| self.can_afford_mount(*a))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The currencies a character has available to spend. Use this to check if
+/// something with a `NormalCost` can be bought
+pub struct Wallet {
+    /// The amount of silver available. 100 silver = 1 gold
+    pub silver: u64,
+    /// The amount of mushrooms available
+    pub mushrooms: u32,
+}
+
+impl Wallet {
+    /// Checks if there is enough silver and mushrooms to pay the given cost
+    #[must_use]
+    pub fn can_afford(&self, cost: NormalCost) -> bool {
+        self.after_spending(cost).is_some()
+    }
+
+    /// The wallet, that would remain after paying the given cost. Returns
+    /// `None`, if either silver, or mushrooms are not sufficient
+    #[must_use]
+    pub fn after_spending(&self, cost: NormalCost) -> Option<Wallet> {
+        Some(Wallet {
+            silver: self.silver.checked_sub(cost.silver)?,
+            mushrooms: self.mushrooms.checked_sub(cost.mushrooms.into())?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Something, that mushrooms can be spent on
+pub enum MushroomSink {
+    /// Buying a beer in the tavern via `Command::BuyBeer`
+    Beer,
+    /// Skipping the remaining time of a quest, or expedition
+    TimeSkip,
+    /// Refreshing the items of a shop via `Command::RefreshShop`
+    ShopRefresh,
+    /// Rerolling the fortress enemy via `Command::FortressNewEnemy`
+    FortressNewEnemy,
+    /// Fighting in the arena before the timer has elapsed
+    ArenaFight,
+    /// Buying the gold frame via `Command::BuyGoldFrame`
+    GoldFrame,
+    /// Anything else. The value can be used to tell these sinks apart
+    Other(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A request to spend mushrooms on a sink up to `count` times, for `cost`
+/// mushrooms each time
+pub struct SpendRequest {
+    /// The thing to spend the mushrooms on
+    pub sink: MushroomSink,
+    /// The amount of mushrooms a single use of the sink costs
+    pub cost: u32,
+    /// The amount of times you want to use the sink at most
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The result of splitting a mushroom budget across multiple sinks
+pub struct SpendPlan {
+    allocations: Vec<(MushroomSink, u32)>,
+    remaining: u32,
+}
+
+impl SpendPlan {
+    /// Splits the budget across the requests. The requests are expected to be
+    /// ordered by priority, so each request gets as many uses as the remaining
+    /// budget allows, before the next one is looked at. A request, that is too
+    /// expensive, does not stop cheaper requests with a lower priority from
+    /// using the rest of the budget
+    #[must_use]
+    pub fn new(budget: u32, requests: &[SpendRequest]) -> SpendPlan {
+        let mut remaining = budget;
+        let mut allocations = Vec::new();
+        for request in requests {
+            let count = match remaining.checked_div(request.cost) {
+                Some(affordable) => affordable.min(request.count),
+                None => request.count,
+            };
+            if count == 0 {
+                continue;
+            }
+            remaining -= count * request.cost;
+            allocations.push((request.sink, count));
+        }
+        SpendPlan {
+            allocations,
+            remaining,
+        }
+    }
+
+    /// The amount of times the given sink should be used. If the same sink
+    /// has been requested multiple times, this is the sum of all of them
+    #[must_use]
+    pub fn count(&self, sink: MushroomSink) -> u32 {
+        self.allocations
+            .iter()
+            .filter(|(s, _)| *s == sink)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// All sinks, that should be used, alongside the amount of times to use
+    /// them. These are in the same order as the requests
+    #[must_use]
+    pub fn allocations(&self) -> &[(MushroomSink, u32)] {
+        &self.allocations
+    }
+
+    /// The mushrooms of the budget, that are left after following this plan
+    #[must_use]
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+}
+
 /// All the exclusively cosmetic info necessary to build a player image, that is
 /// otherwise useless. As these values might change their based on each other,
 /// some of them are not fully parsed (to a more descriptive enum)
@@ -135,6 +371,71 @@ impl Portrait {
                 .unwrap_or_default(),
         })
     }
+
+    /// Returns all the visual parts of this portrait, that an avatar display
+    /// would need to draw it
+    #[must_use]
+    pub fn components(&self) -> PortraitComponents {
+        PortraitComponents {
+            hair_color: self.hair_color,
+            hair: self.hair,
+            mouth: self.mouth,
+            brows: self.brows,
+            eyes: self.eyes,
+            beards: self.beards,
+            nose: self.nose,
+            ears: self.ears,
+            extra: self.extra,
+            horns: self.horns,
+        }
+    }
+
+    /// Converts this portrait back into the raw values the server sends. The
+    /// hair color is encoded in all the parts, that can be colored. The race
+    /// is not part of the portrait, but the server sends it in between, so it
+    /// has to be provided here
+    #[must_use]
+    pub fn to_raw(&self, race: Race) -> [i64; 12] {
+        let colored = |a: u8| i64::from(self.hair_color) * 100 + i64::from(a);
+        [
+            self.mouth.into(),
+            colored(self.hair),
+            colored(self.brows),
+            self.eyes.into(),
+            colored(self.beards),
+            self.nose.into(),
+            self.ears.into(),
+            self.extra.into(),
+            colored(self.horns),
+            self.special_portrait,
+            race as i64,
+            match self.gender {
+                Gender::Male => 1,
+                Gender::Female => 2,
+            },
+        ]
+    }
+}
+
+/// The change of each attribute between two states
+pub type AttributeDelta = EnumMap<AttributeType, i64>;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+/// The visual parts of a portrait. The values are the ids of the images, that
+/// the game uses for each part
+pub struct PortraitComponents {
+    pub hair_color: u8,
+    pub hair: u8,
+    pub mouth: u8,
+    pub brows: u8,
+    pub eyes: u8,
+    pub beards: u8,
+    pub nose: u8,
+    pub ears: u8,
+    pub extra: u8,
+    pub horns: u8,
 }
 
 #[derive(Debug, Clone, Default, Copy, PartialEq, Eq, FromPrimitive, Hash)]
@@ -146,9 +447,14 @@ pub enum Gender {
     Male,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromPrimitive, Hash)]
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, FromPrimitive, Hash, EnumIter,
+)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
+/// The class of a character. All classes, except for the `DemonHunter`, can be
+/// chosen, when registering a new character. Use `Class::registerable()` to
+/// get these
 pub enum Class {
     #[default]
     Warrior = 0,
@@ -156,7 +462,12 @@ pub enum Class {
     Scout,
     Assassin,
     BattleMage,
+    /// Attacks again with a chance of `BERSERKER_CONTINUE_CHANCE` after each
+    /// attack, up to `BERSERKER_MAX_ATTACKS` times per turn. See the
+    /// `simulate` module for these values
     Berserker,
+    /// Has to be unlocked by finishing the demon's portal with another
+    /// character on the same server, so this can not be chosen on register
     DemonHunter,
     Druid,
     Bard,
@@ -166,6 +477,17 @@ pub enum Class {
 
 #[allow(clippy::enum_glob_use)]
 impl Class {
+    /// Returns all classes, that can be chosen in `Command::Register`
+    pub fn registerable() -> impl Iterator<Item = Class> {
+        Class::iter().filter(|a| *a != Class::DemonHunter)
+    }
+
+    /// The id the server uses for this class, when creating a character
+    #[must_use]
+    pub fn registration_id(self) -> usize {
+        self as usize + 1
+    }
+
     #[must_use]
     #[allow(clippy::enum_glob_use)]
     pub fn main_attribute(&self) -> AttributeType {
@@ -287,7 +609,9 @@ impl Race {
     }
 }
 
-#[derive(Debug, Copy, Clone, FromPrimitive, PartialEq, Eq, Hash)]
+#[derive(
+    Debug, Copy, Clone, FromPrimitive, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub enum Mount {
@@ -321,3 +645,141 @@ impl Mount {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn expired_potions_are_not_effective() {
+        let now = Local::now();
+        let potion = |expires| Potion {
+            typ: PotionType::Strength,
+            size: PotionSize::Large,
+            expires,
+        };
+        let character = Character {
+            active_potions: [
+                Some(potion(Some(now + Duration::hours(1)))),
+                Some(potion(Some(now))),
+                Some(potion(None)),
+            ],
+            ..Default::default()
+        };
+
+        let effective = character.effective_potions(now);
+        assert!(effective[0].is_some());
+        assert!(effective[1].is_none());
+        assert!(effective[2].is_some());
+        let later = character.effective_potions(now + Duration::hours(2));
+        assert!(later[0].is_none());
+    }
+
+    fn request(sink: MushroomSink, cost: u32, count: u32) -> SpendRequest {
+        SpendRequest { sink, cost, count }
+    }
+
+    #[test]
+    fn spend_plan_follows_priority() {
+        let plan = SpendPlan::new(
+            10,
+            &[
+                request(MushroomSink::Beer, 1, 3),
+                request(MushroomSink::ShopRefresh, 1, 20),
+                request(MushroomSink::TimeSkip, 1, 5),
+            ],
+        );
+        assert_eq!(plan.count(MushroomSink::Beer), 3);
+        assert_eq!(plan.count(MushroomSink::ShopRefresh), 7);
+        assert_eq!(plan.count(MushroomSink::TimeSkip), 0);
+        assert_eq!(
+            plan.allocations(),
+            [(MushroomSink::Beer, 3), (MushroomSink::ShopRefresh, 7)]
+        );
+        assert_eq!(plan.remaining(), 0);
+    }
+
+    #[test]
+    fn spend_plan_exhausts_the_budget() {
+        let requests = [
+            request(MushroomSink::GoldFrame, 1000, 1),
+            request(MushroomSink::FortressNewEnemy, 3, 2),
+            request(MushroomSink::Other(1), 0, 4),
+            request(MushroomSink::Beer, 1, 10),
+        ];
+        // The gold frame is too expensive, so the cheaper requests get the
+        // budget instead
+        let plan = SpendPlan::new(8, &requests);
+        assert_eq!(plan.count(MushroomSink::GoldFrame), 0);
+        assert_eq!(plan.count(MushroomSink::FortressNewEnemy), 2);
+        assert_eq!(plan.count(MushroomSink::Other(1)), 4);
+        assert_eq!(plan.count(MushroomSink::Beer), 2);
+        assert_eq!(plan.remaining(), 0);
+
+        let plan = SpendPlan::new(1010, &requests);
+        assert_eq!(plan.count(MushroomSink::GoldFrame), 1);
+        assert_eq!(plan.count(MushroomSink::Beer), 4);
+        assert_eq!(plan.remaining(), 0);
+
+        // The character can not spend more, than it has
+        let character = Character {
+            mushrooms: 5,
+            ..Default::default()
+        };
+        let plan = character.spend_plan(1010, &requests);
+        assert_eq!(plan.count(MushroomSink::FortressNewEnemy), 1);
+        assert_eq!(plan.count(MushroomSink::Beer), 2);
+        assert_eq!(plan.remaining(), 0);
+    }
+
+    #[test]
+    fn portrait_to_raw_matches_the_server() {
+        // mouth, hair, brows, eyes, beards, nose, ears, extra, horns,
+        // special portrait, race & gender, as send by the server
+        let raw = [4, 305, 302, 7, 300, 2, 3, 0, 300, 0, 5, 2];
+        let portrait = Portrait::parse(&raw);
+        assert!(portrait.as_ref().is_ok_and(|p| p.gender == Gender::Female));
+        assert_eq!(portrait.map(|p| p.to_raw(Race::Orc)).ok(), Some(raw));
+
+        let raw = [1, 1012, 1001, 3, 1004, 1, 2, 1, 1000, 0, 1, 1];
+        let portrait = Portrait::parse(&raw);
+        assert!(portrait.as_ref().is_ok_and(|p| p.hair_color == 10));
+        assert_eq!(portrait.map(|p| p.to_raw(Race::Human)).ok(), Some(raw));
+    }
+
+    #[test]
+    fn registerable_classes_use_server_ids() {
+        let classes: Vec<_> = Class::registerable().collect();
+        assert!(!classes.contains(&Class::DemonHunter));
+        assert_eq!(classes.len(), Class::iter().count() - 1);
+
+        let ids: Vec<_> = classes.iter().map(|c| c.registration_id()).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5, 6, 8, 9, 10, 11]);
+        for class in classes {
+            // The server sends the same id back, when parsing the character
+            let id = class.registration_id();
+            assert_eq!(Class::from_usize(id - 1), Some(class));
+        }
+    }
+
+    #[test]
+    fn wallet_handles_the_largest_mushroom_cost() {
+        let cost = NormalCost {
+            silver: 100,
+            mushrooms: u16::MAX,
+        };
+        let wallet = |silver, mushrooms| Wallet { silver, mushrooms };
+        let max = u32::from(u16::MAX);
+
+        assert!(!wallet(100, max - 1).can_afford(cost));
+        assert_eq!(wallet(100, max).after_spending(cost), Some(wallet(0, 0)));
+        assert_eq!(
+            wallet(150, max + 1).after_spending(cost),
+            Some(wallet(50, 1))
+        );
+
+        assert!(!wallet(99, max + 1).can_afford(cost));
+    }
+}
@@ -361,3 +361,15 @@ pub fn dungeon_enemy(
         }
     }
 }
+
+/// Returns the monster, that you will have to fight next in the tower, after
+/// having already beaten `finished` floors. The tower is fought with the
+/// character and all companions, so you should use
+/// `BattleFighter::from_squad()` to simulate this fight. Returns `None`, if the
+/// tower has already been finished
+#[must_use]
+pub fn tower_enemy(finished: u16) -> Option<&'static Monster> {
+    LIGHT_ENEMIES
+        .get(LightDungeon::Tower)
+        .get(usize::from(finished))
+}
@@ -104,6 +104,38 @@ impl FortressCost {
             stone: data.csiget(3, "stone cost", u64::MAX)?,
         })
     }
+
+    /// Checks if the wood and stone in the fortress, as well as the available
+    /// silver are enough to pay this cost. The time is not relevant for this
+    #[must_use]
+    pub fn contained_in(
+        &self,
+        resources: &EnumMap<FortressResourceType, FortressResource>,
+        available_silver: u64,
+    ) -> bool {
+        self.stone <= resources.get(FortressResourceType::Stone).current
+            && self.wood <= resources.get(FortressResourceType::Wood).current
+            && self.silver <= available_silver
+    }
+}
+
+impl std::ops::Add for FortressCost {
+    type Output = FortressCost;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        FortressCost {
+            time: self.time.saturating_add(rhs.time),
+            wood: self.wood.saturating_add(rhs.wood),
+            stone: self.stone.saturating_add(rhs.stone),
+            silver: self.silver.saturating_add(rhs.silver),
+        }
+    }
+}
+
+impl std::ops::AddAssign for FortressCost {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -134,7 +166,9 @@ pub struct FortressProduction {
     /// `building_collectable == building_limit` the production stops
     pub limit: u64,
     /// The amount of this resource the corresponding production building
-    /// produces per hour
+    /// produces per hour. None of the known events modify the production of
+    /// the fortress, so this is the actual production, even while the
+    /// `FantasticFortressFestivity` is active
     pub per_hour: u64,
     /// The amount of this resource the building produces on the next level per
     /// hour. If the resource is Experience, this will be 0
@@ -151,6 +185,25 @@ pub enum FortressResourceType {
     Experience = 2,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What upgrading a fortress building mainly improves
+pub enum BuildingCategory {
+    /// The fortress itself and the laborers quarters, which unlock and speed
+    /// up the other buildings
+    Core,
+    /// Buildings, that produce wood, stone, or experience
+    Production,
+    /// Buildings, that increase how much of a resource can be stored
+    Storage,
+    /// The gem mine, which allows searching for gems
+    Gems,
+    /// Buildings, that increase the amount, or strength of units
+    Units,
+    /// The wall, which defends the fortress against attacks
+    Defense,
+}
+
 #[derive(
     Debug, Clone, Copy, EnumCount, FromPrimitive, PartialEq, Eq, Enum, EnumIter,
 )]
@@ -192,6 +245,41 @@ impl FortressBuildingType {
         }
     }
 
+    /// The broad effect upgrading this building has. Useful to weigh
+    /// buildings against each other, based on what you want to achieve
+    #[must_use]
+    pub fn category(self) -> BuildingCategory {
+        match self {
+            FortressBuildingType::Fortress
+            | FortressBuildingType::LaborersQuarters => BuildingCategory::Core,
+            FortressBuildingType::WoodcuttersHut
+            | FortressBuildingType::Quarry
+            | FortressBuildingType::Academy => BuildingCategory::Production,
+            FortressBuildingType::Treasury => BuildingCategory::Storage,
+            FortressBuildingType::GemMine => BuildingCategory::Gems,
+            FortressBuildingType::ArcheryGuild
+            | FortressBuildingType::Barracks
+            | FortressBuildingType::MagesTower
+            | FortressBuildingType::Smithy => BuildingCategory::Units,
+            FortressBuildingType::Wall => BuildingCategory::Defense,
+        }
+    }
+
+    /// The resource, that this building produces, if it produces any
+    #[must_use]
+    pub fn production_resource(self) -> Option<FortressResourceType> {
+        match self {
+            FortressBuildingType::WoodcuttersHut => {
+                Some(FortressResourceType::Wood)
+            }
+            FortressBuildingType::Quarry => Some(FortressResourceType::Stone),
+            FortressBuildingType::Academy => {
+                Some(FortressResourceType::Experience)
+            }
+            _ => None,
+        }
+    }
+
     /// Get the unit type associated with this building type
     #[must_use]
     pub fn unit_produced(self) -> Option<FortressUnitType> {
@@ -281,6 +369,19 @@ pub struct FortressBuilding {
 }
 
 impl Fortress {
+    /// The difference between the combat level of our own wall and the one of
+    /// an enemy (`OtherPlayer::wall_combat_lvl`). A positive value means,
+    /// that our wall is stronger, when defending, than theirs
+    #[must_use]
+    pub fn wall_advantage_over(&self, enemy_wall_lvl: u16) -> i16 {
+        let diff = i32::from(self.wall_combat_lvl) - i32::from(enemy_wall_lvl);
+        i16::try_from(diff).unwrap_or(if diff < 0 {
+            i16::MIN
+        } else {
+            i16::MAX
+        })
+    }
+
     /// Check if units are being trained in the building (soldiers in barracks,
     /// magicians in mages' tower, archers in archery guild), or gem mining is
     /// in progress
@@ -357,9 +458,7 @@ impl Fortress {
             // Check that no construction is in progress
             && self.building_upgrade.target.is_none()
             // Check if there are enough resources
-            && upgrade_cost.stone <= self.resources.get(FortressResourceType::Stone).current
-            && upgrade_cost.wood <= self.resources.get(FortressResourceType::Wood).current
-            && upgrade_cost.silver <= available_silver
+            && upgrade_cost.contained_in(&self.resources, available_silver)
         }
     }
 
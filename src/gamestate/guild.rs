@@ -7,9 +7,12 @@ use num_derive::FromPrimitive;
 use super::{
     items::{ItemType, PotionSize, PotionType},
     update_enum_map, ArrSkip, AttributeType, CCGet, CFPGet, CGet, CSTGet,
-    NormalCost, Potion, SFError, ServerTime,
+    Character, NormalCost, Potion, SFError, ServerTime,
+};
+use crate::{
+    command::Command,
+    misc::{from_sf_string, soft_into, warning_parse},
 };
-use crate::misc::{from_sf_string, soft_into, warning_parse};
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -32,12 +35,18 @@ pub struct Guild {
     /// The date at which the character joined this guild
     pub joined: DateTime<Local>,
 
-    /// The skill you yourself contribute to the guild
+    /// The skill you yourself contribute to the guild. The treasure of the
+    /// guild increases the silver gained from quests. This bonus is already
+    /// applied by the server to the rewards in the tavern and has no effect on
+    /// combat
     pub own_treasure_skill: u16,
     /// The price to pay to upgrade your treasure by one rank
     pub own_treasure_upgrade: NormalCost,
 
-    /// The skill you yourself contribute to the guild
+    /// The skill you yourself contribute to the guild. The instructor of the
+    /// guild increases the experience gained from quests. This bonus is
+    /// already applied by the server to the rewards in the tavern and has no
+    /// effect on combat
     pub own_instructor_skill: u16,
     /// The price to pay to upgrade your instructor by one rank
     pub own_instructor_upgrade: NormalCost,
@@ -178,6 +187,75 @@ impl ChatMessage {
 }
 
 impl Guild {
+    /// Creates the command to change the description and emblem of the
+    /// guild. See `Command::SetDescription` for the length limit of the
+    /// description
+    ///
+    /// # Errors
+    /// Returns an `InvalidRequest` error, if the description is longer than
+    /// `MAX_GUILD_DESCRIPTION_LEN` after escaping
+    #[allow(clippy::unused_self)]
+    pub fn set_info_command(
+        &self,
+        description: &str,
+        emblem: Emblem,
+    ) -> Result<Command, SFError> {
+        let command = Command::GuildSetInfo {
+            description: description.to_string(),
+            emblem,
+        };
+        command.validate()?;
+        Ok(command)
+    }
+
+    /// The level the character has upgraded the given skill to. This is the
+    /// `current` value `Command::GuildIncreaseSkill` expects. The pet skill is
+    /// not tracked here, so this will return None for that
+    #[must_use]
+    pub fn skill_level(&self, skill: GuildSkill) -> Option<u16> {
+        match skill {
+            GuildSkill::Treasure => Some(self.own_treasure_skill),
+            GuildSkill::Instructor => Some(self.own_instructor_skill),
+            GuildSkill::Pet => None,
+        }
+    }
+
+    /// The price to upgrade the given skill by one level. The server does not
+    /// send the price for the pet skill, so this will return None for that
+    #[must_use]
+    pub fn skill_upgrade_cost(&self, skill: GuildSkill) -> Option<NormalCost> {
+        match skill {
+            GuildSkill::Treasure => Some(self.own_treasure_upgrade),
+            GuildSkill::Instructor => Some(self.own_instructor_upgrade),
+            GuildSkill::Pet => None,
+        }
+    }
+
+    /// Checks if the guild is currently able to declare a raid via
+    /// `Command::GuildRaid`. Raids use the same slot as attacks on other
+    /// guilds, so this requires, that no attack/raid is planned and that the
+    /// attack cooldown has run out. Note that only leaders and officers are
+    /// allowed to declare raids
+    #[must_use]
+    pub fn can_declare_raid(&self, server_time: ServerTime) -> bool {
+        self.attacking.is_none()
+            && self
+                .next_attack_possible
+                .is_none_or(|a| server_time.has_passed(a))
+    }
+
+    /// Checks if the character has enough silver and mushrooms to upgrade the
+    /// given skill. If the cost is unknown, this returns false
+    #[must_use]
+    pub fn can_afford_skill(
+        &self,
+        skill: GuildSkill,
+        character: &Character,
+    ) -> bool {
+        self.skill_upgrade_cost(skill)
+            .is_some_and(|cost| character.wallet().can_afford(cost))
+    }
+
     pub(crate) fn update_group_save(
         &mut self,
         val: &str,
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use chrono::{DateTime, Local};
 use enum_map::{Enum, EnumMap};
 use log::{error, warn};
@@ -6,8 +8,8 @@ use num_traits::FromPrimitive;
 use strum::EnumIter;
 
 use super::{
-    unlockables::EquipmentIdent, ArrSkip, CFPGet, Class, EnumMapGet,
-    HabitatType, SFError, ServerTime,
+    dungeons::Dungeon, unlockables::EquipmentIdent, ArrSkip, CFPGet, Class,
+    EnumMapGet, HabitatType, SFError, ServerTime,
 };
 use crate::{
     command::AttributeType,
@@ -52,6 +54,98 @@ impl Inventory {
         bag_free_slots + fortress_chest_free_slots
     }
 
+    /// Returns the item at the given position, if there is one. Equipment is
+    /// not part of the inventory, so this will always be `None` for it
+    #[must_use]
+    pub fn item(&self, place: PlayerItemPlace, pos: usize) -> Option<&Item> {
+        match place {
+            PlayerItemPlace::Equipment => None,
+            PlayerItemPlace::MainInventory => self.bag.get(pos)?.as_ref(),
+            PlayerItemPlace::ExtendedInventory => {
+                self.fortress_chest.as_ref()?.get(pos)?.as_ref()
+            }
+        }
+    }
+
+    /// Returns all items in the inventory (bag & fortress chest). The place
+    /// and position can directly be used in commands like `ItemMove`
+    pub fn items(&self) -> impl Iterator<Item = (ItemPlace, usize, &Item)> {
+        let bag = self
+            .bag
+            .iter()
+            .enumerate()
+            .map(|(pos, item)| (ItemPlace::MainInventory, pos, item));
+        let chest = self
+            .fortress_chest
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(pos, item)| (ItemPlace::FortressChest, pos, item));
+
+        bag.chain(chest)
+            .filter_map(|(place, pos, item)| Some((place, pos, item.as_ref()?)))
+    }
+
+    /// Finds the item with the highest score, that beats the `current` score
+    /// and is not in `skip`. Items, that are not scored, are ignored. Without
+    /// a current score, any scored item is good enough
+    pub(crate) fn best_item(
+        &self,
+        current: Option<u32>,
+        skip: &HashSet<(ItemPlace, usize)>,
+        score: impl Fn(&Item) -> Option<u32>,
+    ) -> Option<(ItemPlace, usize)> {
+        self.items()
+            .filter(|(place, pos, _)| !skip.contains(&(*place, *pos)))
+            .filter_map(|(place, pos, item)| Some((place, pos, score(item)?)))
+            .filter(|(_, _, score)| current.is_none_or(|c| *score > c))
+            .max_by_key(|(_, _, score)| *score)
+            .map(|(place, pos, _)| (place, pos))
+    }
+
+    /// Returns all items in the inventory, that have a rune of the given type.
+    /// The place and position can directly be used in commands like
+    /// `SwapRunes`, or `ItemMove`
+    pub fn items_with_rune(
+        &self,
+        typ: RuneType,
+    ) -> impl Iterator<Item = (ItemPlace, usize, &Item)> {
+        self.items()
+            .filter(move |(_, _, item)| item.rune.is_some_and(|r| r.typ == typ))
+    }
+
+    /// Returns all resource stacks in the inventory with their type and
+    /// amount. The place and position can directly be used in commands like
+    /// `ItemMove`
+    pub fn resource_items(
+        &self,
+    ) -> impl Iterator<Item = (ItemPlace, usize, ResourceType, u32)> + '_ {
+        self.items().filter_map(|(place, pos, item)| {
+            let (typ, amount) = item.typ.resource()?;
+            Some((place, pos, typ, amount))
+        })
+    }
+
+    /// Returns all dungeon keys in the inventory, that could be associated with
+    /// the dungeon they unlock
+    pub fn dungeon_keys(
+        &self,
+    ) -> impl Iterator<Item = (ItemPlace, usize, Dungeon)> + '_ {
+        self.items().filter_map(|(place, pos, item)| {
+            Some((place, pos, item.typ.dungeon_for_key()?))
+        })
+    }
+
+    /// Sums up the amount of all resource stacks in the inventory per type
+    #[must_use]
+    pub fn resource_totals(&self) -> EnumMap<ResourceType, u64> {
+        let mut res = EnumMap::default();
+        for (_, _, typ, amount) in self.resource_items() {
+            *res.get_mut(typ) += u64::from(amount);
+        }
+        res
+    }
+
     pub(crate) fn update_fortress_chest(
         &mut self,
         data: &[i64],
@@ -96,12 +190,30 @@ impl PlayerItemPlace {
     }
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The amount of item slots across the bag and the fortress chest
+pub struct InventoryCapacity {
+    /// The amount of slots, that contain an item
+    pub used: usize,
+    /// The amount of slots, that are empty
+    pub free: usize,
+    /// The amount of slots in total. This is always `used + free`
+    pub total: usize,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
-/// All the parts of `ItemPlace`, that are owned by the player
+/// All the parts of `ItemPlace`, that are owned by the player. Positions in
+/// these are always relative to the start of the specific inventory and start
+/// at 0, so the first fortress chest slot is `(ExtendedInventory, 0)`, not
+/// `(ExtendedInventory, 5)`
 pub enum InventoryType {
+    /// The 5 bag slots, that every character has (`Inventory::bag`)
     MainInventory = 2,
+    /// The slots of the fortress chest (`Inventory::fortress_chest`). This is
+    /// the same as `ItemPlace::FortressChest`
     ExtendedInventory = 5,
 }
 
@@ -117,6 +229,45 @@ impl InventoryType {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A position in the inventory, that counts through the bag first and
+/// continues with the fortress chest. That means 0..=4 are the bag slots and 5
+/// is the first slot of the fortress chest. This is the order the inventory is
+/// displayed in the game
+pub struct BagPosition(pub usize);
+
+impl BagPosition {
+    /// Converts this into the inventory and the position in it, that commands
+    /// expect. The first slot of the fortress chest will be
+    /// `(ExtendedInventory, 0)`, which `item_position()` converts to
+    /// `ItemPlace::FortressChest`. Returns `None`, if the fortress chest is
+    /// not unlocked, or does not have that many slots
+    #[must_use]
+    pub fn inventory_pos(
+        self,
+        inventory: &Inventory,
+    ) -> Option<(PlayerItemPlace, usize)> {
+        let bag_len = inventory.bag.len();
+        if self.0 < bag_len {
+            return Some((PlayerItemPlace::MainInventory, self.0));
+        }
+        let chest_len = inventory.fortress_chest.as_ref().map_or(0, Vec::len);
+        let pos = self.0 - bag_len;
+        (pos < chest_len).then_some((PlayerItemPlace::ExtendedInventory, pos))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The places, that consume items, which are dropped into them
+pub enum DropTarget {
+    /// The toilet (`Command::ToiletDrop`)
+    Toilet,
+    /// The cauldron of the witch (`Command::WitchDropCauldron`)
+    WitchCauldron,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// All places, that items can be dragged to excluding companions
@@ -129,10 +280,47 @@ pub enum ItemPlace {
     WeaponShop = 3,
     /// The items in the mage slot
     MageShop = 4,
-    /// The items in the fortress chest slots
+    /// The items in the fortress chest slots. This is the same as
+    /// `InventoryType::ExtendedInventory`
     FortressChest = 5,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The thing, that should be maximized, when choosing which items to equip
+pub enum LoadoutObjective {
+    /// Maximize the main attribute of the class (strength, dexterity, or
+    /// intelligence)
+    MaxMainAttribute,
+    /// Maximize the constitution, which determines the hit points
+    MaxHp,
+}
+
+impl LoadoutObjective {
+    /// How valuable this item is for the objective, if equipped by the class.
+    /// This includes the attributes of the gem socketed in the item
+    #[must_use]
+    pub fn score(self, item: &Item, class: Class) -> u32 {
+        self.score_for(item, class, false)
+    }
+
+    /// How valuable this item is for the objective, if equipped by the class.
+    /// Gems in weapons only count double for the character, not companions
+    #[must_use]
+    pub fn score_for(
+        self,
+        item: &Item,
+        class: Class,
+        is_companion: bool,
+    ) -> u32 {
+        let attribute = match self {
+            LoadoutObjective::MaxMainAttribute => class.main_attribute(),
+            LoadoutObjective::MaxHp => AttributeType::Constitution,
+        };
+        *item.total_attributes(class, is_companion).get(attribute)
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// All the equipment a player is wearing
@@ -149,6 +337,36 @@ impl Equipment {
         false
     }
 
+    /// Returns all equipped items, that do not have a rune yet. The position of
+    /// these items in commands like `SwapRunes` is `slot as usize - 1`
+    pub fn items_without_rune(
+        &self,
+    ) -> impl Iterator<Item = (EquipmentSlot, &Item)> {
+        self.0.iter().filter_map(|(slot, item)| {
+            let item = item.as_ref()?;
+            item.rune.is_none().then_some((slot, item))
+        })
+    }
+
+    /// Sums up all the attributes, that the equipped items and their gems
+    /// give to a character of the given class. Gems in weapons count double,
+    /// unless the equipment belongs to a companion
+    #[must_use]
+    pub fn attributes(
+        &self,
+        class: Class,
+        is_companion: bool,
+    ) -> EnumMap<AttributeType, u32> {
+        let mut total = EnumMap::default();
+
+        for equip in self.0.iter().flat_map(|a| a.1) {
+            for (k, v) in equip.total_attributes(class, is_companion) {
+                *total.get_mut(k) += v;
+            }
+        }
+        total
+    }
+
     /// Expects the input `data` to have items directly at data[0]
     pub(crate) fn parse(
         data: &[i64],
@@ -165,8 +383,10 @@ impl Equipment {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 /// Information about a single item. This can be anything, that is either in a
-/// inventory, in a reward slot, or similar
+/// inventory, in a reward slot, or similar. Use `Item::new()`, if you want to
+/// create one yourself
 pub struct Item {
     /// The type of this item. May contain further type specific values
     pub typ: ItemType,
@@ -199,6 +419,61 @@ pub struct Item {
 }
 
 impl Item {
+    /// Sums up the attributes of this item and its gem, when worn by a
+    /// character of the given class. Gems in weapons count double, unless the
+    /// item is worn by a companion
+    #[must_use]
+    pub fn total_attributes(
+        &self,
+        class: Class,
+        is_companion: bool,
+    ) -> EnumMap<AttributeType, u32> {
+        let mut total = self.attributes;
+
+        if let Some(GemSlot::Filled(gem)) = &self.gem_slot {
+            let value = self.typ.equipment_slot().map_or(gem.value, |slot| {
+                gem.effective_value(slot, is_companion)
+            });
+
+            let mut add_atr = |at| *total.get_mut(at) += value;
+            match gem.typ {
+                GemType::All => {
+                    total.iter_mut().for_each(|a| *a.1 += value);
+                }
+                GemType::Legendary => {
+                    add_atr(AttributeType::Constitution);
+                    add_atr(class.main_attribute());
+                }
+                typ => {
+                    if let Some(at) = typ.attribute() {
+                        add_atr(at);
+                    }
+                }
+            }
+        }
+        total
+    }
+
+    /// Creates a new item of the given type without any attributes, gems,
+    /// runes, or enchantments. All other values can be set afterwards. This is
+    /// mainly useful to create items for simulations, or tests
+    #[must_use]
+    pub fn new(typ: ItemType) -> Item {
+        Item {
+            typ,
+            price: 0,
+            mushroom_price: 0,
+            model_id: 0,
+            class: None,
+            type_specific_val: 0,
+            attributes: EnumMap::default(),
+            gem_slot: None,
+            rune: None,
+            enchantment: None,
+            color: 0,
+        }
+    }
+
     /// Maps an item to its ident. This is mainly useful, if you want to see,
     /// if a item is already in your scrapbook
     #[must_use]
@@ -248,6 +523,26 @@ impl Item {
         self.typ.is_enchantable()
     }
 
+    /// Computes the `raw_model_id`, that `ChangeItemLook` expects to change
+    /// the look of this item to the given model id. This is the inverse of
+    /// how items are parsed: `model_id + (class as u16) * 1000`.
+    ///
+    /// Returns `None`, if this item is not equipment, if it is a class item
+    /// without a class, or if the model id is not valid
+    #[must_use]
+    pub fn raw_look_id(&self, target_model: u16) -> Option<u16> {
+        self.typ.equipment_slot()?;
+        if target_model >= 1000 {
+            return None;
+        }
+        let class_offset = match self.class {
+            Some(class) => class as u16 * 1000,
+            None if self.typ.is_class_item() => return None,
+            None => 0,
+        };
+        Some(class_offset + target_model)
+    }
+
     /// Checks if a companion of the given class can equip this item.
     ///
     /// Returns `true` if the item itself is equipment and this class has the
@@ -493,6 +788,39 @@ pub struct Rune {
     pub value: u8,
 }
 
+/// The maximum elemental resistance in percent, that a single rune can give.
+/// This is not send by the server and only used for `effective_resistance()`.
+/// The simulation uses the raw rune values
+pub const MAX_RUNE_RESISTANCE: u8 = 70;
+/// The maximum elemental damage bonus in percent, that a single rune can
+/// give. This is not send by the server and only used for
+/// `effective_damage_bonus()`. The simulation uses the raw rune values
+pub const MAX_RUNE_DAMAGE: u8 = 60;
+
+impl Rune {
+    /// The resistance in percent, that this rune actually gives, after
+    /// applying the `MAX_RUNE_RESISTANCE` cap. This is 0 for runes, that do
+    /// not give any resistance
+    #[must_use]
+    pub fn effective_resistance(self) -> u8 {
+        if !self.typ.is_resistance() {
+            return 0;
+        }
+        self.value.min(MAX_RUNE_RESISTANCE)
+    }
+
+    /// The elemental damage bonus in percent, that this rune actually gives,
+    /// after applying the `MAX_RUNE_DAMAGE` cap. This is 0 for runes, that do
+    /// not give any elemental damage
+    #[must_use]
+    pub fn effective_damage_bonus(self) -> u8 {
+        if !self.typ.is_damage() {
+            return 0;
+        }
+        self.value.min(MAX_RUNE_DAMAGE)
+    }
+}
+
 #[derive(Debug, Clone, Copy, FromPrimitive, PartialEq, Eq, EnumIter, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
@@ -512,9 +840,54 @@ pub enum RuneType {
     LightningDamage,
 }
 
+impl RuneType {
+    /// A human readable name of this rune type
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            RuneType::QuestGold => "Quest Gold",
+            RuneType::EpicChance => "Epic Chance",
+            RuneType::ItemQuality => "Item Quality",
+            RuneType::QuestXP => "Quest XP",
+            RuneType::ExtraHitPoints => "Extra Hit Points",
+            RuneType::FireResistance => "Fire Resistance",
+            RuneType::ColdResistence => "Cold Resistance",
+            RuneType::LightningResistance => "Lightning Resistance",
+            RuneType::TotalResistence => "Total Resistance",
+            RuneType::FireDamage => "Fire Damage",
+            RuneType::ColdDamage => "Cold Damage",
+            RuneType::LightningDamage => "Lightning Damage",
+        }
+    }
+
+    /// Checks if this rune gives resistance against elemental damage
+    #[must_use]
+    pub fn is_resistance(self) -> bool {
+        matches!(
+            self,
+            RuneType::FireResistance
+                | RuneType::ColdResistence
+                | RuneType::LightningResistance
+                | RuneType::TotalResistence
+        )
+    }
+
+    /// Checks if this rune gives elemental damage
+    #[must_use]
+    pub fn is_damage(self) -> bool {
+        matches!(
+            self,
+            RuneType::FireDamage
+                | RuneType::ColdDamage
+                | RuneType::LightningDamage
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-/// A gem slot for an item
+/// A gem slot for an item. The game does not restrict gem types to certain
+/// item types, so any gem fits into any empty socket
 pub enum GemSlot {
     /// This gemslot has been filled and can only be emptied by the blacksmith
     Filled(Gem),
@@ -523,6 +896,14 @@ pub enum GemSlot {
 }
 
 impl GemSlot {
+    /// Checks if the given gem could be inserted into this slot. Since any
+    /// gem type fits into any socket, this only depends on the slot being
+    /// empty
+    #[must_use]
+    pub fn accepts(&self, _gem: &Gem) -> bool {
+        matches!(self, GemSlot::Empty)
+    }
+
     pub(crate) fn parse(slot_val: i64, gem_pwr: i64) -> Option<GemSlot> {
         match slot_val {
             0 => return None,
@@ -651,6 +1032,38 @@ impl ItemType {
         )
     }
 
+    /// The type and amount of resources this item contains, if it is a
+    /// resource stack
+    #[must_use]
+    pub fn resource(&self) -> Option<(ResourceType, u32)> {
+        match self {
+            ItemType::Resource { amount, typ } => Some((*typ, *amount)),
+            _ => None,
+        }
+    }
+
+    /// The dungeon, that this key unlocks. Only the keys of the first 11
+    /// dungeons are numbered in the order of the dungeons (1..=11) and the
+    /// matching shadow keys are offset by 50 (51..=61). The other keys
+    /// (17, 19, 22, 69, 70 and 62..=68 for shadow dungeons) are known to be
+    /// dungeon keys, but we do not know which dungeon they unlock, so they
+    /// will return `None`
+    #[must_use]
+    pub fn dungeon_for_key(&self) -> Option<Dungeon> {
+        let ItemType::DungeonKey { id, shadow_key } = *self else {
+            return None;
+        };
+        match (id, shadow_key) {
+            (1..=11, false) => {
+                Some(Dungeon::Light(FromPrimitive::from_u32(id - 1)?))
+            }
+            (51..=61, true) => {
+                Some(Dungeon::Shadow(FromPrimitive::from_u32(id - 51)?))
+            }
+            _ => None,
+        }
+    }
+
     /// The equipment slot, that this item type can be equipped to
     #[must_use]
     pub fn equipment_slot(&self) -> Option<EquipmentSlot> {
@@ -854,6 +1267,26 @@ impl From<AttributeType> for PotionType {
 }
 
 impl PotionType {
+    /// Checks if this is the potion of eternal life, which increases the hit
+    /// points instead of an attribute
+    #[must_use]
+    pub fn is_life_potion(self) -> bool {
+        self == PotionType::EternalLife
+    }
+
+    /// The thing, that this potion increases in a fight
+    #[must_use]
+    pub fn combat_effect(self) -> PotionEffect {
+        PotionEffect::Attribute(match self {
+            PotionType::Strength => AttributeType::Strength,
+            PotionType::Dexterity => AttributeType::Dexterity,
+            PotionType::Intelligence => AttributeType::Intelligence,
+            PotionType::Constitution => AttributeType::Constitution,
+            PotionType::Luck => AttributeType::Luck,
+            PotionType::EternalLife => return PotionEffect::HitPoints,
+        })
+    }
+
     pub(crate) fn parse(id: i64) -> Option<PotionType> {
         if id == 0 {
             return None;
@@ -871,6 +1304,16 @@ impl PotionType {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The thing, that a potion increases in a fight
+pub enum PotionEffect {
+    /// The attribute is increased by the percentage of the `PotionSize`
+    Attribute(AttributeType),
+    /// The hit points are increased by 25%
+    HitPoints,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
@@ -901,7 +1344,9 @@ impl PotionSize {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Copy, FromPrimitive)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, Copy, FromPrimitive, Enum, EnumIter, Hash,
+)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 /// Differentiates resource items
@@ -923,6 +1368,24 @@ pub struct Gem {
     pub value: u32,
 }
 
+impl Gem {
+    /// The value this gem adds to each of its attributes, when socketed in an
+    /// item in the given slot. Gems in weapons count double, unless they are
+    /// worn by a companion
+    #[must_use]
+    pub fn effective_value(
+        &self,
+        slot: EquipmentSlot,
+        is_companion: bool,
+    ) -> u32 {
+        if slot == EquipmentSlot::Weapon && !is_companion {
+            self.value * 2
+        } else {
+            self.value
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
@@ -938,6 +1401,20 @@ pub enum GemType {
 }
 
 impl GemType {
+    /// The single attribute, that this gem increases. `All` and `Legendary`
+    /// gems increase multiple attributes, so this returns `None` for them
+    #[must_use]
+    pub fn attribute(self) -> Option<AttributeType> {
+        Some(match self {
+            GemType::Strength => AttributeType::Strength,
+            GemType::Dexterity => AttributeType::Dexterity,
+            GemType::Intelligence => AttributeType::Intelligence,
+            GemType::Constitution => AttributeType::Constitution,
+            GemType::Luck => AttributeType::Luck,
+            GemType::All | GemType::Legendary => return None,
+        })
+    }
+
     pub(crate) fn parse(id: i64, debug_value: u32) -> Option<GemType> {
         Some(match id {
             0 | 1 => return None,
@@ -1044,3 +1521,164 @@ impl PetItem {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use super::*;
+
+    #[test]
+    fn loadout_score_includes_gems() {
+        let mut item = Item::new(ItemType::Hat);
+        *item.attributes.get_mut(AttributeType::Strength) = 100;
+        *item.attributes.get_mut(AttributeType::Constitution) = 50;
+        let objective = LoadoutObjective::MaxMainAttribute;
+        assert_eq!(objective.score(&item, Class::Warrior), 100);
+
+        item.gem_slot = Some(GemSlot::Filled(Gem {
+            typ: GemType::Legendary,
+            value: 30,
+        }));
+        assert_eq!(objective.score(&item, Class::Warrior), 130);
+        assert_eq!(LoadoutObjective::MaxHp.score(&item, Class::Warrior), 80);
+
+        let mut equipment = Equipment::default();
+        *equipment.0.get_mut(EquipmentSlot::Hat) = Some(item.clone());
+        assert_eq!(
+            equipment.attributes(Class::Warrior, false),
+            item.total_attributes(Class::Warrior, false)
+        );
+    }
+
+    #[test]
+    fn dungeon_key_mapping() {
+        use crate::gamestate::dungeons::{LightDungeon, ShadowDungeon};
+
+        let key = |id, shadow_key| {
+            ItemType::DungeonKey { id, shadow_key }.dungeon_for_key()
+        };
+        assert_eq!(
+            key(1, false),
+            Some(Dungeon::Light(LightDungeon::DesecratedCatacombs))
+        );
+        assert_eq!(
+            key(11, false),
+            Some(Dungeon::Light(LightDungeon::CircusOfHorror))
+        );
+        assert_eq!(
+            key(51, true),
+            Some(Dungeon::Shadow(ShadowDungeon::DesecratedCatacombs))
+        );
+        assert_eq!(
+            key(61, true),
+            Some(Dungeon::Shadow(ShadowDungeon::CircusOfHorror))
+        );
+        for id in [17, 19, 22, 69, 70] {
+            assert_eq!(key(id, false), None);
+        }
+        for id in 62..=68 {
+            assert_eq!(key(id, true), None);
+        }
+        assert_eq!(ItemType::ToiletKey.dungeon_for_key(), None);
+    }
+
+    #[test]
+    fn bag_position_boundaries() {
+        let mut inventory = Inventory::default();
+        let pos = |p, inv: &Inventory| BagPosition(p).inventory_pos(inv);
+
+        assert_eq!(
+            pos(0, &inventory),
+            Some((PlayerItemPlace::MainInventory, 0))
+        );
+        assert_eq!(
+            pos(4, &inventory),
+            Some((PlayerItemPlace::MainInventory, 4))
+        );
+        // Without a fortress chest, there is nothing after the bag
+        assert_eq!(pos(5, &inventory), None);
+
+        inventory.fortress_chest = Some(vec![None; 3]);
+        assert_eq!(
+            pos(4, &inventory),
+            Some((PlayerItemPlace::MainInventory, 4))
+        );
+        assert_eq!(
+            pos(5, &inventory),
+            Some((PlayerItemPlace::ExtendedInventory, 0))
+        );
+        assert_eq!(
+            pos(7, &inventory),
+            Some((PlayerItemPlace::ExtendedInventory, 2))
+        );
+        assert_eq!(pos(8, &inventory), None);
+
+        assert_eq!(
+            PlayerItemPlace::ExtendedInventory.item_position(),
+            ItemPlace::FortressChest
+        );
+        assert_eq!(
+            InventoryType::ExtendedInventory.item_position(),
+            ItemPlace::FortressChest
+        );
+    }
+
+    #[test]
+    fn rune_caps() {
+        let rune = |typ, value| Rune { typ, value };
+
+        let at_cap = rune(RuneType::FireResistance, MAX_RUNE_RESISTANCE);
+        assert_eq!(at_cap.effective_resistance(), 70);
+        let above = rune(RuneType::FireResistance, MAX_RUNE_RESISTANCE + 1);
+        assert_eq!(above.effective_resistance(), 70);
+        assert_eq!(above.effective_damage_bonus(), 0);
+
+        let at_cap = rune(RuneType::ColdDamage, MAX_RUNE_DAMAGE);
+        assert_eq!(at_cap.effective_damage_bonus(), 60);
+        let above = rune(RuneType::ColdDamage, 99);
+        assert_eq!(above.effective_damage_bonus(), 60);
+        assert_eq!(above.effective_resistance(), 0);
+    }
+
+    #[test]
+    fn potion_combat_effects() {
+        for attr in AttributeType::iter() {
+            let typ = PotionType::from(attr);
+            assert!(!typ.is_life_potion());
+            assert_eq!(typ.combat_effect(), PotionEffect::Attribute(attr));
+        }
+        assert!(PotionType::EternalLife.is_life_potion());
+        assert_eq!(
+            PotionType::EternalLife.combat_effect(),
+            PotionEffect::HitPoints
+        );
+    }
+
+    #[test]
+    fn any_gem_fits_an_empty_socket() {
+        use GemType::{
+            All, Constitution, Dexterity, Intelligence, Legendary, Luck,
+            Strength,
+        };
+        let filled = GemSlot::Filled(Gem {
+            typ: Luck,
+            value: 10,
+        });
+        for typ in [
+            Strength,
+            Dexterity,
+            Intelligence,
+            Constitution,
+            Luck,
+            All,
+            Legendary,
+        ] {
+            let gem = Gem { typ, value: 100 };
+            assert!(GemSlot::Empty.accepts(&gem));
+            assert!(!filled.accepts(&gem));
+        }
+        assert_eq!(GemSlot::parse(1, 0), Some(GemSlot::Empty));
+        assert_eq!(GemSlot::parse(0, 0), None);
+    }
+}
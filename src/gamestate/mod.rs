@@ -11,7 +11,7 @@ pub mod tavern;
 pub mod underworld;
 pub mod unlockables;
 
-use std::{borrow::Borrow, collections::HashSet};
+use std::{borrow::Borrow, collections::HashSet, fmt::Write as _, sync::Arc};
 
 use chrono::{DateTime, Duration, Local, NaiveDateTime};
 use enum_map::EnumMap;
@@ -82,8 +82,70 @@ pub struct GameState {
     /// The amount of sec, that the server is ahead of us in seconds (can be
     /// negative)
     server_time_diff: i64,
+    /// All the keys (and their values) from the last update, that we did not
+    /// know how to handle
+    #[cfg_attr(feature = "serde", serde(skip))]
+    ignored_keys: Vec<(String, String)>,
+    /// The features, that were not available before the last update, but are
+    /// now
+    #[cfg_attr(feature = "serde", serde(skip))]
+    newly_unlocked: Vec<Feature>,
+    /// The callback, that gets notified about anomalies during parsing
+    #[cfg_attr(feature = "serde", serde(skip))]
+    parse_observer: ParseObserver,
 }
 
+#[derive(Debug)]
+#[non_exhaustive]
+/// Something noteworthy, that happened while parsing a response. These are
+/// the same things, that are logged as warnings, but in a structured form. See
+/// `GameState::set_parse_observer()`
+pub enum ParseEvent {
+    /// The response contained a key, that we do not know how to handle
+    IgnoredKey {
+        /// The key, that was ignored
+        key: String,
+        /// The value of the key. Cut off after 500 chars
+        value: String,
+    },
+    /// An entry in a list could not be parsed (too short, invalid, etc.) and
+    /// was skipped. The rest of the list was still parsed
+    SkippedEntry(SFError),
+    /// The server send a value for something, that we do not know yet
+    UnknownValue {
+        /// What the value describes
+        name: &'static str,
+        /// The raw value
+        value: String,
+    },
+}
+
+type ParseObserverFn = dyn Fn(ParseEvent) + Send + Sync;
+
+#[derive(Clone, Default)]
+struct ParseObserver(Option<Arc<ParseObserverFn>>);
+
+impl std::fmt::Debug for ParseObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ParseObserver")
+            .field(&self.0.is_some())
+            .finish()
+    }
+}
+
+impl ParseObserver {
+    fn report(&self, event: ParseEvent) {
+        if let Some(observer) = &self.0 {
+            observer(event);
+        }
+    }
+}
+
+/// The maximum amount of ignored keys, that we remember from a single update
+const MAX_IGNORED_KEYS: usize = 50;
+/// The maximum length of the value of an ignored key, that we remember
+const MAX_IGNORED_VAL_LEN: usize = 500;
+
 const SHOP_N: usize = 6;
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -114,6 +176,19 @@ impl Default for Shop {
 }
 
 impl Shop {
+    /// Returns all items in this shop, that have a rune of the given type
+    /// together with their position in the shop. The position can be used as
+    /// the `shop_pos` in `BuyShop`
+    pub fn items_with_rune(
+        &self,
+        typ: RuneType,
+    ) -> impl Iterator<Item = (usize, &Item)> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(move |(_, item)| item.rune.is_some_and(|r| r.typ == typ))
+    }
+
     pub(crate) fn parse(
         data: &[i64],
         server_time: ServerTime,
@@ -182,6 +257,10 @@ impl GameState {
 
         self.last_fight = None;
         self.mail.open_claimable = None;
+        self.ignored_keys.clear();
+        let previously_unlocked: Vec<_> =
+            Feature::iter().filter(|a| self.is_unlocked(*a)).collect();
+        let observer = self.parse_observer.clone();
 
         let mut other_player: Option<OtherPlayer> = None;
         let mut other_guild: Option<OtherGuild> = None;
@@ -585,7 +664,10 @@ impl GameState {
                             Ok(x) => {
                                 self.hall_of_fames.players.push(x);
                             }
-                            Err(err) => warn!("{err}"),
+                            Err(err) => {
+                                warn!("{err}");
+                                observer.report(ParseEvent::SkippedEntry(err));
+                            }
                         }
                     }
                 }
@@ -596,7 +678,10 @@ impl GameState {
                             Ok(x) => {
                                 self.hall_of_fames.guilds.push(x);
                             }
-                            Err(err) => warn!("{err}"),
+                            Err(err) => {
+                                warn!("{err}");
+                                observer.report(ParseEvent::SkippedEntry(err));
+                            }
                         }
                     }
                 }
@@ -615,7 +700,10 @@ impl GameState {
                             Ok(x) => {
                                 self.hall_of_fames.pets.push(x);
                             }
-                            Err(err) => warn!("{err}"),
+                            Err(err) => {
+                                warn!("{err}");
+                                observer.report(ParseEvent::SkippedEntry(err));
+                            }
                         }
                     }
                 }
@@ -626,7 +714,10 @@ impl GameState {
                             Ok(x) => {
                                 self.hall_of_fames.fortresses.push(x);
                             }
-                            Err(err) => warn!("{err}"),
+                            Err(err) => {
+                                warn!("{err}");
+                                observer.report(ParseEvent::SkippedEntry(err));
+                            }
                         }
                     }
                 }
@@ -637,7 +728,10 @@ impl GameState {
                             Ok(x) => {
                                 self.hall_of_fames.underworlds.push(x);
                             }
-                            Err(err) => warn!("{err}"),
+                            Err(err) => {
+                                warn!("{err}");
+                                observer.report(ParseEvent::SkippedEntry(err));
+                            }
                         }
                     }
                 }
@@ -817,6 +911,13 @@ impl GameState {
                         *item = match FromPrimitive::from_i64(x) {
                             None if x != 0 => {
                                 warn!("Unknown item: {x}");
+                                observer.report(ParseEvent::UnknownValue {
+                                    name: "expedition item",
+                                    value: x.to_string(),
+                                });
+                                if !exp.unknown_values.contains(&x) {
+                                    exp.unknown_values.push(x);
+                                }
                                 Some(ExpeditionThing::Unknown)
                             }
                             x => x,
@@ -882,7 +983,10 @@ impl GameState {
                     {
                         match InboxEntry::parse(msg, server_time) {
                             Ok(msg) => self.mail.inbox.push(msg),
-                            Err(e) => warn!("Invalid msg: {msg} {e}"),
+                            Err(e) => {
+                                warn!("Invalid msg: {msg} {e}");
+                                observer.report(ParseEvent::SkippedEntry(e));
+                            }
                         };
                     }
                 }
@@ -905,6 +1009,7 @@ impl GameState {
                                     "Unable to parse combat log entry: \
                                      {parts:?} - {e}"
                                 );
+                                observer.report(ParseEvent::SkippedEntry(e));
                             }
                         }
                     }
@@ -1356,6 +1461,19 @@ impl GameState {
                 }
                 "pendingrewards" => {
                     let vals: Vec<_> = val.as_str().split('/').collect();
+                    let rest = vals.chunks_exact(6).remainder();
+                    if !rest.iter().all(|a| a.is_empty()) {
+                        warn!(
+                            "Pending rewards have weird size: {} {rest:?}",
+                            vals.len()
+                        );
+                        observer.report(ParseEvent::SkippedEntry(
+                            SFError::ParsingError(
+                                "pending rewards",
+                                rest.join("/"),
+                            ),
+                        ));
+                    }
                     self.mail.claimables = vals
                         .chunks_exact(6)
                         .flat_map(|chunk| -> Result<ClaimableMail, SFError> {
@@ -1368,6 +1486,10 @@ impl GameState {
                                 "2" => ClaimableStatus::Claimed,
                                 x => {
                                     warn!("Unknown claimable status: {x}");
+                                    observer.report(ParseEvent::UnknownValue {
+                                        name: "claimable status",
+                                        value: x.to_string(),
+                                    });
                                     ClaimableStatus::Claimed
                                 }
                             };
@@ -1430,6 +1552,18 @@ impl GameState {
                 }
                 x => {
                     warn!("Update ignored {x} -> {val:?}");
+                    let val: String = val
+                        .as_str()
+                        .chars()
+                        .take(MAX_IGNORED_VAL_LEN)
+                        .collect();
+                    if self.ignored_keys.len() < MAX_IGNORED_KEYS {
+                        self.ignored_keys.push((x.to_string(), val.clone()));
+                    }
+                    observer.report(ParseEvent::IgnoredKey {
+                        key: x.to_string(),
+                        value: val,
+                    });
                 }
             }
         }
@@ -1474,9 +1608,45 @@ impl GameState {
             self.witch = None;
         }
 
+        // The event times are only send in `gttime`, but the hellevator needs
+        // them to know if the final reward can be claimed
+        let event = &mut self.hellevator;
+        if let Some(hellevator) = &mut event.active {
+            hellevator.event_end = event.end;
+            hellevator.collect_time_end = event.collect_time_end;
+        }
+
+        self.newly_unlocked = Feature::iter()
+            .filter(|a| {
+                self.is_unlocked(*a) && !previously_unlocked.contains(a)
+            })
+            .collect();
+
         Ok(())
     }
 
+    /// Checks if the given feature is currently available to the player
+    #[must_use]
+    pub fn is_unlocked(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::Fortress => self.fortress.is_some(),
+            Feature::Underworld => self.underworld.is_some(),
+            Feature::Witch => self.witch.is_some(),
+            Feature::Portal => self.dungeons.portal.is_some(),
+            Feature::Pets => self.pets.is_some(),
+            Feature::Blacksmith => self.blacksmith.is_some(),
+            Feature::IdleGame => self.idle_game.is_some(),
+        }
+    }
+
+    /// Returns the features, that became available during the last update,
+    /// so that you can notify the user about them. Note that on the first
+    /// update (login), everything the player has is new
+    #[must_use]
+    pub fn newly_unlocked_features(&self) -> &[Feature] {
+        &self.newly_unlocked
+    }
+
     pub(crate) fn updatete_relation_list(&mut self, val: &str) {
         self.character.relations.clear();
         for entry in val
@@ -1701,6 +1871,595 @@ impl GameState {
         Ok(())
     }
 
+    /// Returns all the currencies, that can currently be used to skip a wait
+    /// (quests, expeditions, etc.). Glasses are listed first, since they are
+    /// the cheaper option. Skipping with mushrooms is only possible, if the
+    /// player allowed it in the settings
+    #[must_use]
+    pub fn available_time_skips(&self) -> Vec<TimeSkip> {
+        let mut res = Vec::new();
+        if self.tavern.quicksand_glasses > 0 {
+            res.push(TimeSkip::Glass);
+        }
+        if self.tavern.mushroom_skip_allowed
+            && self.character.can_afford_mushrooms(1)
+        {
+            res.push(TimeSkip::Mushroom);
+        }
+        res
+    }
+
+    /// Counts the used & free item slots across all storage the character has
+    /// for items (the bag and the fortress chest, if unlocked)
+    #[must_use]
+    pub fn inventory_capacity(&self) -> InventoryCapacity {
+        let inventory = &self.character.inventory;
+        let total = inventory.bag.len()
+            + inventory.fortress_chest.as_ref().map_or(0, Vec::len);
+        let free = inventory.count_free_slots();
+        InventoryCapacity {
+            used: total - free,
+            free,
+            total,
+        }
+    }
+
+    /// Creates the command to drop the item at `bag_pos` into the target.
+    /// Returns `None`, if there is no such slot
+    #[must_use]
+    pub fn drop_command_for(
+        &self,
+        bag_pos: BagPosition,
+        target: DropTarget,
+    ) -> Option<Command> {
+        let (place, pos) = bag_pos.inventory_pos(&self.character.inventory)?;
+        Some(match target {
+            DropTarget::Toilet => Command::ToiletDrop {
+                inventory: place,
+                pos,
+            },
+            DropTarget::WitchCauldron => Command::WitchDropCauldron {
+                inventory_t: place,
+                position: pos,
+            },
+        })
+    }
+
+    /// Checks if the quest at `pos` could be started right now via
+    /// `Command::StartQuest`. The exact length of a quest depends on the
+    /// mount and enchantments, so only a completely empty thirst for
+    /// adventure is rejected here
+    ///
+    /// # Errors
+    /// Returns an `InvalidRequest` error, if the character is busy, if there
+    /// is no quest at that position, if the expedition setting disables
+    /// quests, or if the character has no thirst for adventure left
+    pub fn can_start_quest(&self, pos: usize) -> Result<(), SFError> {
+        let tavern = &self.tavern;
+        if !tavern.is_idle() {
+            return Err(SFError::InvalidRequest("The character is busy"));
+        }
+        if tavern.quests.get(pos).is_none_or(|a| a.base_length == 0) {
+            return Err(SFError::InvalidRequest("There is no quest at pos"));
+        }
+        if tavern.questing_preference == ExpeditionSetting::PreferExpeditions
+            && tavern.expeditions.is_event_ongoing()
+        {
+            return Err(SFError::InvalidRequest(
+                if tavern.can_change_questing_preference() {
+                    "Quests are disabled. Change the questing preference first"
+                } else {
+                    "Quests are disabled by the questing preference for today"
+                },
+            ));
+        }
+        if tavern.thirst_for_adventure_sec == 0 {
+            return Err(SFError::InvalidRequest(
+                "No thirst for adventure left",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks if the expedition at `pos` could be started right now via
+    /// `Command::ExpeditionStart`
+    ///
+    /// # Errors
+    /// Returns an `InvalidRequest` error, if the character is busy, if the
+    /// expedition event is not going on, if there is no expedition at that
+    /// position, if the expedition setting disables expeditions, or if the
+    /// character does not have enough thirst for adventure left
+    pub fn can_start_expedition(&self, pos: usize) -> Result<(), SFError> {
+        let tavern = &self.tavern;
+        if !tavern.is_idle() {
+            return Err(SFError::InvalidRequest("The character is busy"));
+        }
+        if !tavern.expeditions.is_event_ongoing() {
+            return Err(SFError::InvalidRequest(
+                "Expeditions are not available right now",
+            ));
+        }
+        let Some(expedition) = tavern.expeditions.available.get(pos) else {
+            return Err(SFError::InvalidRequest(
+                "There is no expedition at pos",
+            ));
+        };
+        if tavern.questing_preference == ExpeditionSetting::PreferQuests {
+            return Err(SFError::InvalidRequest(
+                if tavern.can_change_questing_preference() {
+                    "Expeditions are disabled. Change the questing preference \
+                     first"
+                } else {
+                    "Expeditions are disabled by the questing preference for \
+                     today"
+                },
+            ));
+        }
+        if tavern.thirst_for_adventure_sec < expedition.thirst_for_adventure_sec
+        {
+            return Err(SFError::InvalidRequest(
+                "Not enough thirst for adventure left",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Creates the command to equip the item at `bag_pos` into the given
+    /// slot
+    ///
+    /// # Errors
+    /// Returns an `InvalidRequest` error, if there is no item at that
+    /// position, or if the item can not be equipped into that slot by the
+    /// class of the character
+    pub fn equip_command(
+        &self,
+        bag_pos: BagPosition,
+        slot: EquipmentSlot,
+    ) -> Result<Command, SFError> {
+        let inventory = &self.character.inventory;
+        let (place, pos) = bag_pos
+            .inventory_pos(inventory)
+            .ok_or(SFError::InvalidRequest("Invalid inventory position"))?;
+        let item = inventory
+            .item(place, pos)
+            .ok_or(SFError::InvalidRequest("There is no item to equip"))?;
+        if item.typ.equipment_slot() != Some(slot) {
+            return Err(SFError::InvalidRequest(
+                "The item can not be equipped in this slot",
+            ));
+        }
+        if !item.can_be_equipped_by(self.character.class) {
+            return Err(SFError::InvalidRequest(
+                "The item can not be equipped by this class",
+            ));
+        }
+        Ok(Command::ItemMove {
+            from: place.item_position(),
+            from_pos: pos,
+            to: ItemPlace::Equipment,
+            to_pos: slot as usize - 1,
+        })
+    }
+
+    /// Creates the command to sell the item at `bag_pos` to a shop. Unlike
+    /// `Command::SellShop`, this does not pick a random shop slot. Instead the
+    /// slot with the least valuable item is chosen, so that items you might
+    /// want to buy with mushrooms are not replaced. Returns `None`, if there is
+    /// no item at that position
+    #[must_use]
+    pub fn sell_command(&self, bag_pos: BagPosition) -> Option<Command> {
+        let inventory = &self.character.inventory;
+        let (place, pos) = bag_pos.inventory_pos(inventory)?;
+        inventory.item(place, pos)?;
+
+        let (shop, shop_pos, _) = ShopType::iter()
+            .flat_map(|shop| {
+                self.shops
+                    .get(shop)
+                    .items
+                    .iter()
+                    .enumerate()
+                    .map(move |(pos, item)| (shop, pos, item))
+            })
+            .min_by_key(|(_, _, item)| {
+                (
+                    !matches!(item.typ, ItemType::Unknown(0)),
+                    item.mushroom_price,
+                    item.price,
+                )
+            })?;
+
+        Some(Command::ItemMove {
+            from: place.item_position(),
+            from_pos: pos,
+            to: shop.item_position(),
+            to_pos: shop_pos,
+        })
+    }
+
+    /// The item at the given position of the given shop. Returns `None`, if
+    /// the position is out of bounds
+    #[must_use]
+    pub fn shop_item(&self, shop: ShopType, pos: usize) -> Option<&Item> {
+        self.shops.get(shop).items.get(pos)
+    }
+
+    /// A mutable reference to the item at the given position of the given
+    /// shop. Returns `None`, if the position is out of bounds
+    #[must_use]
+    pub fn shop_item_mut(
+        &mut self,
+        shop: ShopType,
+        pos: usize,
+    ) -> Option<&mut Item> {
+        self.shops.get_mut(shop).items.get_mut(pos)
+    }
+
+    /// Finds the best rune of the given type in the inventory and an equipped
+    /// item, that could use it. That is either an item without a rune, or an
+    /// item with a weaker rune of the same type. The result is
+    /// `(from, from_pos)` & `(to, to_pos)`, which can directly be used in a
+    /// `SwapRunes` command
+    #[must_use]
+    pub fn rune_swap_plan(
+        &self,
+        rune: RuneType,
+    ) -> Option<((ItemPlace, usize), (ItemPlace, usize))> {
+        let (from, from_pos, best) = self
+            .character
+            .inventory
+            .items_with_rune(rune)
+            .max_by_key(|(_, _, item)| item.rune.map(|a| a.value))?;
+        let best_value = best.rune.map(|a| a.value)?;
+
+        let equipment = &self.character.equipment;
+        let target = equipment.items_without_rune().next().or_else(|| {
+            equipment
+                .0
+                .iter()
+                .filter_map(|(slot, item)| Some((slot, item.as_ref()?)))
+                .filter(|(_, item)| {
+                    item.rune
+                        .is_some_and(|r| r.typ == rune && r.value < best_value)
+                })
+                .min_by_key(|(_, item)| item.rune.map(|a| a.value))
+        });
+        let (slot, _) = target?;
+        Some(((from, from_pos), (ItemPlace::Equipment, slot as usize - 1)))
+    }
+
+    /// Finds the items in the inventory, that are better for the objective
+    /// than the ones currently equipped by the character and the companions
+    /// and returns the commands to equip them. Empty slots are always filled,
+    /// if a fitting item exists. The character gets the first pick, so an
+    /// item is only given to a companion, if the character does not use it.
+    /// Assassins can also equip a second weapon in their shield slot. The
+    /// replaced items will be moved to the position the new item was at. This
+    /// does not send anything, so you have to send the commands yourself (in
+    /// order)
+    #[must_use]
+    pub fn optimize_loadout(
+        &self,
+        objective: LoadoutObjective,
+    ) -> Vec<Command> {
+        let inventory = &self.character.inventory;
+        let mut used = HashSet::new();
+        let mut commands = Vec::new();
+
+        let class = self.character.class;
+        for (slot, equipped) in &self.character.equipment.0 {
+            let current = equipped.as_ref().map(|a| objective.score(a, class));
+            let best = inventory.best_item(current, &used, |item| {
+                let fits = item.typ.equipment_slot() == Some(slot)
+                    || class == Class::Assassin
+                        && slot == EquipmentSlot::Shield
+                        && item.typ.is_weapon();
+                (fits && item.can_be_equipped_by(class))
+                    .then(|| objective.score(item, class))
+            });
+            let Some((from, from_pos)) = best else {
+                continue;
+            };
+            used.insert((from, from_pos));
+            commands.push(Command::ItemMove {
+                from,
+                from_pos,
+                to: ItemPlace::Equipment,
+                to_pos: slot as usize - 1,
+            });
+        }
+
+        let Some(companions) = &self.dungeons.companions else {
+            return commands;
+        };
+        for (companion, info) in companions {
+            let class = companion.into();
+            for (slot, equipped) in &info.equipment.0 {
+                let current = equipped
+                    .as_ref()
+                    .map(|a| objective.score_for(a, class, true));
+                let best = inventory.best_item(current, &used, |item| {
+                    (item.typ.equipment_slot() == Some(slot)
+                        && item.can_be_equipped_by_companion(companion))
+                    .then(|| objective.score_for(item, class, true))
+                });
+                let Some((from, from_pos)) = best else {
+                    continue;
+                };
+                used.insert((from, from_pos));
+                let from_inventory = match from {
+                    ItemPlace::FortressChest => {
+                        InventoryType::ExtendedInventory
+                    }
+                    _ => InventoryType::MainInventory,
+                };
+                let Ok(from_pos) = from_pos.try_into() else {
+                    continue;
+                };
+                commands.push(Command::EquipCompanion {
+                    from_inventory,
+                    from_pos,
+                    to_companion: companion,
+                    to_slot: slot,
+                });
+            }
+        }
+        commands
+    }
+
+    /// Returns all the keys (and their values), that the last update did not
+    /// know how to handle. If the server adds something new, it will show up
+    /// here, so this is a good place to look for things to report/parse. To
+    /// keep this small, only the first 50 keys are stored and their values are
+    /// cut off after 500 chars
+    #[must_use]
+    pub fn ignored_keys(&self) -> &[(String, String)] {
+        &self.ignored_keys
+    }
+
+    /// Sets a callback, that will be called with every `ParseEvent`, that
+    /// happens during `update()`. This is meant for monitoring, if you do not
+    /// use the `log` crate. The log messages will still be emitted. Setting a
+    /// new observer replaces the previous one
+    pub fn set_parse_observer(
+        &mut self,
+        observer: impl Fn(ParseEvent) + Send + Sync + 'static,
+    ) {
+        self.parse_observer = ParseObserver(Some(Arc::new(observer)));
+    }
+
+    /// Removes the callback set in `set_parse_observer()`
+    pub fn clear_parse_observer(&mut self) {
+        self.parse_observer = ParseObserver(None);
+    }
+
+    /// Compares all top level fields of this game state with the ones of
+    /// `other` and returns a human readable description of every field, that
+    /// differs. This is meant as a debugging/testing aid to compare the results
+    /// of parsing the same responses, so the comparison is done on the debug
+    /// representation of each field and thus fairly slow
+    #[cfg(feature = "diff")]
+    #[must_use]
+    pub fn diff_fields(&self, other: &GameState) -> Vec<String> {
+        macro_rules! diff {
+            ($res:ident, $($field:ident),+ $(,)?) => {$(
+                let a = format!("{:?}", self.$field);
+                let b = format!("{:?}", other.$field);
+                if a != b {
+                    $res.push(format!(
+                        "{}: {a} != {b}",
+                        stringify!($field)
+                    ));
+                }
+            )+};
+        }
+
+        let mut res = Vec::new();
+        diff!(
+            res,
+            character,
+            tavern,
+            arena,
+            last_fight,
+            shops,
+            guild,
+            specials,
+            dungeons,
+            underworld,
+            fortress,
+            pets,
+            hellevator,
+            blacksmith,
+            witch,
+            achievements,
+            idle_game,
+            pending_unlocks,
+            hall_of_fames,
+            lookup,
+            mail,
+            server_time_diff,
+        );
+        res
+    }
+
+    /// Exports a small, stable subset of this game state as a JSON string.
+    /// This works without the `serde` feature and the schema will only ever
+    /// be extended, so it is a good fit for dashboards and other external
+    /// tools. The schema is:
+    ///
+    /// ```json
+    /// {
+    ///   "name": "string",
+    ///   "level": 0,
+    ///   "class": "Warrior",
+    ///   "attributes": { "Strength": { "base": 0, "bonus": 0 }, ... },
+    ///   "equipment": { "Hat": { "armor": 0, "attributes": { ... } }, ... },
+    ///   "resources": {
+    ///     "silver": 0, "mushrooms": 0, "honor": 0,
+    ///     "wood": null, "stone": null, "souls": null
+    ///   }
+    /// }
+    /// ```
+    ///
+    /// Empty equipment slots are `null`. Fortress & underworld resources are
+    /// `null`, if the feature has not been unlocked yet
+    #[must_use]
+    pub fn summary_json(&self) -> String {
+        fn attributes_json(attributes: &EnumMap<AttributeType, u32>) -> String {
+            let values: Vec<_> = attributes
+                .iter()
+                .map(|(typ, val)| format!("\"{typ:?}\":{val}"))
+                .collect();
+            format!("{{{}}}", values.join(","))
+        }
+        fn opt_json(val: Option<u64>) -> String {
+            val.map_or_else(|| "null".to_string(), |a| a.to_string())
+        }
+
+        let character = &self.character;
+
+        let attributes: Vec<_> = AttributeType::iter()
+            .map(|typ| {
+                format!(
+                    "\"{typ:?}\":{{\"base\":{},\"bonus\":{}}}",
+                    character.attribute_basis.get(typ),
+                    character.attribute_additions.get(typ)
+                )
+            })
+            .collect();
+
+        let equipment: Vec<_> = character
+            .equipment
+            .0
+            .iter()
+            .map(|(slot, item)| match item {
+                Some(item) => format!(
+                    "\"{slot:?}\":{{\"armor\":{},\"attributes\":{}}}",
+                    item.armor(),
+                    attributes_json(&item.attributes)
+                ),
+                None => format!("\"{slot:?}\":null"),
+            })
+            .collect();
+
+        let fortress_resource =
+            |typ| self.fortress.as_ref().map(|a| a.resources.get(typ).current);
+        let resources = format!(
+            "{{\"silver\":{},\"mushrooms\":{},\"honor\":{},\
+             \"wood\":{},\"stone\":{},\"souls\":{}}}",
+            character.silver,
+            character.mushrooms,
+            character.honor,
+            opt_json(fortress_resource(FortressResourceType::Wood)),
+            opt_json(fortress_resource(FortressResourceType::Stone)),
+            opt_json(self.underworld.as_ref().map(|a| a.souls_current)),
+        );
+
+        format!(
+            "{{\"name\":{},\"level\":{},\"class\":\"{:?}\",\
+             \"attributes\":{{{}}},\"equipment\":{{{}}},\
+             \"resources\":{resources}}}",
+            json_string(&character.name),
+            character.level,
+            character.class,
+            attributes.join(","),
+            equipment.join(","),
+        )
+    }
+
+    /// Collects all known points in time in the future, at which something
+    /// becomes available, or finishes. The timers are sorted, so the first
+    /// one is the next thing, that will happen. A bot can just sleep until
+    /// then, instead of checking each timer on its own
+    #[must_use]
+    pub fn schedule(&self) -> Schedule {
+        let mut timers = vec![
+            (
+                TimerType::ExpeditionWait,
+                self.tavern.expeditions.active().and_then(|a| a.busy_until),
+            ),
+            (TimerType::ArenaFight, self.arena.next_free_fight),
+            (TimerType::DungeonFight, self.dungeons.next_free_fight),
+            (TimerType::Calendar, self.specials.calendar.next_possible),
+            (TimerType::DiceGame, self.tavern.dice_game.next_free),
+            (TimerType::Wheel, self.specials.wheel.next_free_spin),
+        ];
+        match self.tavern.current_action {
+            CurrentAction::Quest { busy_until, .. } => {
+                timers.push((TimerType::Quest, Some(busy_until)));
+            }
+            CurrentAction::CityGuard { busy_until, .. } => {
+                timers.push((TimerType::CityGuard, Some(busy_until)));
+            }
+            _ => {}
+        }
+        if let Some(pets) = &self.pets {
+            timers
+                .push((TimerType::PetExploration, pets.next_free_exploration));
+            timers.push((TimerType::PetBattle, pets.opponent.next_free_battle));
+        }
+        if let Some(fortress) = &self.fortress {
+            timers.push((
+                TimerType::FortressBuilding,
+                fortress.building_upgrade.finish,
+            ));
+            timers.push((
+                TimerType::FortressGemSearch,
+                fortress.gem_search.finish,
+            ));
+        }
+        if let Some(underworld) = &self.underworld {
+            timers.push((
+                TimerType::UnderworldBuilding,
+                underworld.upgrade_finish,
+            ));
+        }
+
+        let now = Local::now();
+        let mut timers: Vec<_> = timers
+            .into_iter()
+            .filter_map(|(typ, time)| Some((typ, time.filter(|a| *a > now)?)))
+            .map(|(typ, time)| Timer { typ, time })
+            .collect();
+        timers.sort_by_key(|a| a.time);
+        Schedule { timers }
+    }
+
+    /// Creates a copy of this game state, that only contains the state of the
+    /// player. The hall of fames, the lookup of other players/guilds and the
+    /// mails are left empty. This is useful, if you want to keep a history of
+    /// the character without storing all the other things over and over
+    #[must_use]
+    pub fn player_snapshot(&self) -> GameState {
+        GameState {
+            character: self.character.clone(),
+            tavern: self.tavern.clone(),
+            arena: self.arena.clone(),
+            last_fight: self.last_fight.clone(),
+            shops: self.shops.clone(),
+            guild: self.guild.clone(),
+            specials: self.specials.clone(),
+            dungeons: self.dungeons.clone(),
+            underworld: self.underworld.clone(),
+            fortress: self.fortress.clone(),
+            pets: self.pets.clone(),
+            hellevator: self.hellevator.clone(),
+            blacksmith: self.blacksmith.clone(),
+            witch: self.witch.clone(),
+            achievements: self.achievements.clone(),
+            idle_game: self.idle_game.clone(),
+            pending_unlocks: self.pending_unlocks.clone(),
+            hall_of_fames: HallOfFames::default(),
+            lookup: Lookup::default(),
+            mail: Mail::default(),
+            last_request_timestamp: self.last_request_timestamp,
+            server_time_diff: self.server_time_diff,
+            ignored_keys: Vec::new(),
+            newly_unlocked: self.newly_unlocked.clone(),
+            parse_observer: self.parse_observer.clone(),
+        }
+    }
+
     /// Returns the time of the server. This is just an 8 byte copy behind the
     /// scenes, so feel free to NOT cache/optimize calling this in any way
     #[must_use]
@@ -1708,6 +2467,24 @@ impl GameState {
         ServerTime(self.server_time_diff)
     }
 
+    /// Returns the (local) time at which the server has send the last response,
+    /// that this game state has been updated with. This is useful to figure
+    /// out how fresh the data is. If no response with a timestamp has been
+    /// received yet, this returns None
+    #[must_use]
+    pub fn last_update_time(&self) -> Option<DateTime<Local>> {
+        self.server_time()
+            .convert_to_local(self.last_request_timestamp, "last update")
+    }
+
+    /// The amount of seconds, that the server is ahead of us (can be
+    /// negative). All timestamps of the server are converted to local time by
+    /// using this offset
+    #[must_use]
+    pub fn server_time_offset(&self) -> i64 {
+        self.server_time_diff
+    }
+
     /// Given a header value like "fight4", this would give you the
     /// corresponding fight[3]. In case that does not exist, it will be created
     /// w/ the default
@@ -1768,6 +2545,13 @@ impl ServerTime {
         Local::now().naive_local() + Duration::seconds(self.0)
     }
 
+    /// Checks if the given time, that has been converted to local time while
+    /// parsing, is now in the past according to the server
+    #[must_use]
+    pub fn has_passed(&self, time: DateTime<Local>) -> bool {
+        time.naive_local() + Duration::seconds(self.0) <= self.current()
+    }
+
     #[must_use]
     pub fn next_midnight(&self) -> std::time::Duration {
         let current = self.current();
@@ -1781,6 +2565,28 @@ impl ServerTime {
     }
 }
 
+/// Converts the string into a quoted JSON string literal
+fn json_string(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if c.is_control() => {
+                // Writing to a String can not fail
+                let _ = write!(res, "\\u{:04x}", u32::from(c));
+            }
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
 // https://stackoverflow.com/a/59955929
 trait StringSetExt {
     fn set(&mut self, s: &str);
@@ -1795,8 +2601,55 @@ impl StringSetExt for String {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+/// The thing a `Timer` is waiting for
+pub enum TimerType {
+    Quest,
+    CityGuard,
+    ExpeditionWait,
+    ArenaFight,
+    DungeonFight,
+    PetExploration,
+    PetBattle,
+    Calendar,
+    DiceGame,
+    Wheel,
+    FortressBuilding,
+    FortressGemSearch,
+    UnderworldBuilding,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A point in time, at which something will be available/finished
+pub struct Timer {
+    /// What this timer is for
+    pub typ: TimerType,
+    /// The time at which this timer elapses
+    pub time: DateTime<Local>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// All the timers, that have not yet elapsed. See `GameState::schedule()`
+pub struct Schedule {
+    /// The timers sorted by the time at which they elapse
+    pub timers: Vec<Timer>,
+}
+
+impl Schedule {
+    /// The timer, that will elapse next
+    #[must_use]
+    pub fn next(&self) -> Option<&Timer> {
+        self.timers.first()
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 /// The cost of something
 pub struct NormalCost {
     /// The amount of silver something costs
@@ -1804,3 +2657,388 @@ pub struct NormalCost {
     /// The amount of mushrooms something costs
     pub mushrooms: u16,
 }
+
+impl NormalCost {
+    /// Creates a new cost of the given amount of silver and mushrooms
+    #[must_use]
+    pub const fn new(silver: u64, mushrooms: u16) -> NormalCost {
+        NormalCost { silver, mushrooms }
+    }
+
+    /// Checks if the given wallet has enough silver and mushrooms to pay this
+    /// cost
+    #[must_use]
+    pub fn contained_in(&self, wallet: &Wallet) -> bool {
+        wallet.can_afford(*self)
+    }
+}
+
+impl std::ops::Add for NormalCost {
+    type Output = NormalCost;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        NormalCost {
+            silver: self.silver.saturating_add(rhs.silver),
+            mushrooms: self.mushrooms.saturating_add(rhs.mushrooms),
+        }
+    }
+}
+
+impl std::ops::AddAssign for NormalCost {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    fn request(command: Option<Command>) -> Option<String> {
+        command?.request_string().ok()
+    }
+
+    #[test]
+    fn drop_command_uses_the_right_inventory() {
+        let mut gs = GameState::default();
+        gs.character.inventory.fortress_chest = Some(vec![None; 5]);
+
+        let drop = |pos, target| gs.drop_command_for(BagPosition(pos), target);
+        assert_eq!(
+            request(drop(4, DropTarget::Toilet)).as_deref(),
+            Some("PlayerToilettLoad:2/5")
+        );
+        assert_eq!(
+            request(drop(5, DropTarget::Toilet)).as_deref(),
+            Some("PlayerToilettLoad:5/1")
+        );
+        assert_eq!(
+            request(drop(5, DropTarget::WitchCauldron)).as_deref(),
+            Some("PlayerWitchSpendItem:5/1")
+        );
+        assert!(drop(10, DropTarget::Toilet).is_none());
+    }
+
+    #[test]
+    fn equip_command_checks_class_and_slot() {
+        let mut gs = GameState::default();
+        gs.character.class = Class::Warrior;
+
+        let mut hat = Item::new(ItemType::Hat);
+        hat.class = Some(Class::Warrior);
+        gs.character.inventory.bag[4] = Some(hat.clone());
+        hat.class = Some(Class::Mage);
+        gs.character.inventory.fortress_chest = Some(vec![Some(hat), None]);
+
+        let equip = |pos, slot| gs.equip_command(BagPosition(pos), slot);
+        assert_eq!(
+            request(equip(4, EquipmentSlot::Hat).ok()).as_deref(),
+            Some("PlayerItemMove:2/5/1/1")
+        );
+        // Right slot, but the wrong class
+        assert!(equip(5, EquipmentSlot::Hat).is_err());
+        // Right class, but the wrong slot
+        assert!(equip(4, EquipmentSlot::Belt).is_err());
+        // Empty slots
+        assert!(equip(0, EquipmentSlot::Hat).is_err());
+        assert!(equip(6, EquipmentSlot::Hat).is_err());
+        assert!(equip(7, EquipmentSlot::Hat).is_err());
+    }
+
+    #[test]
+    fn sell_command_prefers_empty_shop_slots() {
+        let mut gs = GameState::default();
+        gs.character.inventory.bag[0] = Some(Item::new(ItemType::Ring));
+        for (_, shop) in &mut gs.shops {
+            for item in &mut shop.items {
+                *item = Item::new(ItemType::Hat);
+                item.price = 1000;
+                item.mushroom_price = 5;
+            }
+        }
+        if let Some(item) = gs.shop_item_mut(ShopType::Weapon, 3) {
+            item.mushroom_price = 0;
+        }
+        let sell = |gs: &GameState, pos| gs.sell_command(BagPosition(pos));
+
+        // No empty slot, so the least valuable item gets replaced
+        assert_eq!(
+            request(sell(&gs, 0)).as_deref(),
+            Some("PlayerItemMove:2/1/3/4")
+        );
+
+        if let Some(item) = gs.shop_item_mut(ShopType::Magic, 2) {
+            item.typ = ItemType::Unknown(0);
+        }
+        assert_eq!(
+            request(sell(&gs, 0)).as_deref(),
+            Some("PlayerItemMove:2/1/4/3")
+        );
+        // There is no item to sell
+        assert!(sell(&gs, 1).is_none());
+    }
+
+    #[test]
+    fn shop_items_with_rune() {
+        let mut shop = Shop::default();
+        let rune = |typ| {
+            let mut item = Item::new(ItemType::Hat);
+            item.rune = Some(Rune { typ, value: 10 });
+            item
+        };
+        shop.items[1] = rune(RuneType::FireResistance);
+        shop.items[3] = rune(RuneType::ExtraHitPoints);
+        shop.items[4] = rune(RuneType::FireResistance);
+
+        let positions: Vec<_> = shop
+            .items_with_rune(RuneType::FireResistance)
+            .map(|(pos, _)| pos)
+            .collect();
+        assert_eq!(positions, vec![1, 4]);
+        assert_eq!(shop.items_with_rune(RuneType::ColdDamage).count(), 0);
+    }
+
+    fn gear(typ: ItemType, class: Class, strength: u32) -> Item {
+        let mut item = Item::new(typ);
+        item.class = Some(class);
+        *item.attributes.get_mut(AttributeType::Strength) = strength;
+        item
+    }
+
+    fn equip(slot: EquipmentSlot, from_pos: usize) -> Command {
+        Command::ItemMove {
+            from: ItemPlace::MainInventory,
+            from_pos,
+            to: ItemPlace::Equipment,
+            to_pos: slot as usize - 1,
+        }
+    }
+
+    #[test]
+    fn optimize_loadout_fills_multiple_slots() {
+        let mut gs = GameState::default();
+        gs.character.class = Class::Warrior;
+        *gs.character.equipment.0.get_mut(EquipmentSlot::Hat) =
+            Some(gear(ItemType::Hat, Class::Warrior, 10));
+        gs.character.inventory.bag = [
+            Some(gear(ItemType::Hat, Class::Warrior, 5)),
+            Some(gear(ItemType::Hat, Class::Warrior, 20)),
+            // Empty slots are filled, even if the item does not help
+            Some(gear(ItemType::Gloves, Class::Warrior, 0)),
+            Some(gear(ItemType::Ring, Class::Mage, 50)),
+            None,
+        ];
+
+        let commands = gs.optimize_loadout(LoadoutObjective::MaxMainAttribute);
+        assert_eq!(
+            commands,
+            vec![
+                equip(EquipmentSlot::Hat, 1),
+                equip(EquipmentSlot::Gloves, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn optimize_loadout_gives_assassins_two_weapons() {
+        let mut gs = GameState::default();
+        gs.character.class = Class::Assassin;
+        let weapon = ItemType::Weapon {
+            min_dmg: 10,
+            max_dmg: 20,
+        };
+        gs.character.inventory.bag = [
+            Some(gear(weapon, Class::Warrior, 0)),
+            Some(gear(weapon, Class::Warrior, 0)),
+            Some(gear(
+                ItemType::Shield { block_chance: 10 },
+                Class::Warrior,
+                0,
+            )),
+            None,
+            None,
+        ];
+
+        let commands = gs.optimize_loadout(LoadoutObjective::MaxHp);
+        assert_eq!(commands.len(), 2);
+        assert!(commands.contains(&equip(EquipmentSlot::Weapon, 1)));
+        assert!(commands.contains(&equip(EquipmentSlot::Shield, 0)));
+    }
+
+    #[test]
+    fn optimize_loadout_equips_companions() {
+        let mut gs = GameState::default();
+        gs.character.class = Class::Warrior;
+        gs.dungeons.companions = Some(EnumMap::default());
+        gs.character.inventory.bag = [
+            Some(gear(ItemType::Hat, Class::Warrior, 30)),
+            Some(gear(ItemType::Hat, Class::Warrior, 20)),
+            Some(gear(ItemType::Hat, Class::Mage, 10)),
+            Some(gear(
+                ItemType::Shield { block_chance: 10 },
+                Class::Warrior,
+                5,
+            )),
+            None,
+        ];
+        gs.character.inventory.fortress_chest =
+            Some(vec![None, Some(gear(ItemType::Belt, Class::Scout, 1))]);
+        *gs.character.equipment.0.get_mut(EquipmentSlot::Shield) = Some(gear(
+            ItemType::Shield { block_chance: 10 },
+            Class::Warrior,
+            50,
+        ));
+
+        let companion = |from_inventory, from_pos, to_companion, to_slot| {
+            Command::EquipCompanion {
+                from_inventory,
+                from_pos,
+                to_companion,
+                to_slot,
+            }
+        };
+        let commands = gs.optimize_loadout(LoadoutObjective::MaxMainAttribute);
+        assert_eq!(
+            commands,
+            vec![
+                // The character gets the first pick
+                equip(EquipmentSlot::Hat, 0),
+                companion(
+                    InventoryType::MainInventory,
+                    1,
+                    CompanionClass::Warrior,
+                    EquipmentSlot::Hat
+                ),
+                companion(
+                    InventoryType::MainInventory,
+                    2,
+                    CompanionClass::Mage,
+                    EquipmentSlot::Hat
+                ),
+                companion(
+                    InventoryType::ExtendedInventory,
+                    1,
+                    CompanionClass::Scout,
+                    EquipmentSlot::Belt
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_pending_rewards_are_reported() {
+        let skipped = Arc::new(Mutex::new(Vec::new()));
+        let mut gs = GameState::default();
+        let events = skipped.clone();
+        gs.set_parse_observer(move |event| {
+            if let ParseEvent::SkippedEntry(SFError::ParsingError(name, v)) =
+                event
+            {
+                if let Ok(mut events) = events.lock() {
+                    events.push((name, v));
+                }
+            }
+        });
+
+        update(&mut gs, "pendingrewards:1/0/1/code/0/0");
+        update(&mut gs, "pendingrewards:1/0/1/code/0/0/2/1");
+        assert_eq!(gs.mail.claimables.len(), 1);
+
+        let skipped = skipped.lock().map(|s| s.clone()).unwrap_or_default();
+        assert_eq!(skipped, vec![("pending rewards", "2/1".to_string())]);
+    }
+
+    #[test]
+    fn player_snapshot_drops_the_caches() {
+        let mut gs = GameState::default();
+        gs.character.level = 123;
+        gs.hall_of_fames.players_total = 5000;
+        gs.hall_of_fames.players.push(HallOfFamePlayer::default());
+        gs.lookup.insert_lookup(OtherPlayer {
+            player_id: 42,
+            name: "other".to_string(),
+            ..Default::default()
+        });
+        gs.lookup
+            .guilds
+            .insert("guild".to_string(), OtherGuild::default());
+        gs.mail.inbox_capacity = 100;
+        gs.mail.open_msg = Some("message".to_string());
+
+        let snapshot = gs.player_snapshot();
+        assert_eq!(snapshot.character.level, 123);
+        assert_eq!(snapshot.hall_of_fames.players_total, 0);
+        assert!(snapshot.hall_of_fames.players.is_empty());
+        assert!(snapshot.lookup.lookup_pid(42).is_none());
+        assert!(snapshot.lookup.lookup_name("other").is_none());
+        assert!(snapshot.lookup.guilds.is_empty());
+        assert_eq!(snapshot.mail.inbox_capacity, 0);
+        assert!(snapshot.mail.open_msg.is_none());
+
+        // The original state is left untouched
+        assert!(gs.lookup.lookup_pid(42).is_some());
+        assert_eq!(gs.hall_of_fames.players.len(), 1);
+    }
+
+    /// A minimal player save of a human warrior with the given level
+    fn player_save(level: i64) -> String {
+        let mut save = vec![0; 800];
+        let shop_items =
+            (0..6).flat_map(|i| [(288 + i * 12, 1), (361 + i * 12, 1)]);
+        for (pos, val) in
+            [(7, level), (27, 1), (29, 1)].into_iter().chain(shop_items)
+        {
+            if let Some(v) = save.get_mut(pos) {
+                *v = val;
+            }
+        }
+        let save: Vec<_> = save.iter().map(i64::to_string).collect();
+        format!("ownplayersave:{}", save.join("/"))
+    }
+
+    fn update(gs: &mut GameState, body: &str) {
+        let resp =
+            Response::parse(body.to_string(), Local::now().naive_local());
+        assert!(resp.is_ok_and(|r| gs.update(r).is_ok()));
+    }
+
+    #[test]
+    fn level_up_unlocks_the_fortress() {
+        let mut gs = GameState::default();
+        update(&mut gs, &player_save(24));
+        assert_eq!(gs.character.level, 24);
+        assert!(!gs.is_unlocked(Feature::Fortress));
+        assert!(gs.newly_unlocked_features().is_empty());
+
+        update(&mut gs, &player_save(25));
+        assert_eq!(gs.newly_unlocked_features(), &[Feature::Fortress]);
+
+        // Only the update, that unlocked it, reports it as new
+        update(&mut gs, &player_save(26));
+        assert!(gs.newly_unlocked_features().is_empty());
+        assert!(gs.is_unlocked(Feature::Fortress));
+    }
+
+    #[test]
+    fn first_update_reports_everything_as_new() {
+        let mut gs = GameState::default();
+        let body = format!(
+            "{}&resources:{}&petsrank:1",
+            player_save(100),
+            ["0"; 18].join("/")
+        );
+        update(&mut gs, &body);
+
+        let unlocked: Vec<_> =
+            Feature::iter().filter(|a| gs.is_unlocked(*a)).collect();
+        for feature in [Feature::Fortress, Feature::Blacksmith, Feature::Pets] {
+            assert!(unlocked.contains(&feature), "{feature:?}");
+        }
+        assert_eq!(gs.newly_unlocked_features(), unlocked.as_slice());
+
+        update(&mut gs, &body);
+        assert!(gs.newly_unlocked_features().is_empty());
+    }
+}
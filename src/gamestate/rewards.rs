@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 use chrono::{DateTime, Local};
 use log::warn;
@@ -670,7 +670,7 @@ pub struct Reward {
     pub amount: u64,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Copy, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Hash, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RewardType {
     HellevatorPoints,
@@ -743,7 +743,62 @@ impl RewardType {
     }
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(from = "Vec<(RewardType, u64)>", into = "Vec<(RewardType, u64)>")
+)]
+/// The summed up amounts of multiple rewards, grouped by their type
+pub struct RewardTotals {
+    amounts: BTreeMap<RewardType, u64>,
+}
+
+impl RewardTotals {
+    /// The total amount of the given reward type. If there is no reward of
+    /// that type, this will be 0
+    #[must_use]
+    pub fn get(&self, typ: RewardType) -> u64 {
+        self.amounts.get(&typ).copied().unwrap_or_default()
+    }
+
+    /// Iterates over all reward types, that have been part of the
+    /// aggregation, alongside their total amount. The types are always in
+    /// the same order, in which they are declared in `RewardType`
+    pub fn iter(&self) -> impl Iterator<Item = (RewardType, u64)> + '_ {
+        self.amounts.iter().map(|(typ, amount)| (*typ, *amount))
+    }
+}
+
+// Reward types with a value can not be keys of a JSON object, so the totals
+// are (de)serialized as a list of pairs instead
+impl From<Vec<(RewardType, u64)>> for RewardTotals {
+    fn from(value: Vec<(RewardType, u64)>) -> Self {
+        Self {
+            amounts: value.into_iter().collect(),
+        }
+    }
+}
+
+impl From<RewardTotals> for Vec<(RewardType, u64)> {
+    fn from(value: RewardTotals) -> Self {
+        value.amounts.into_iter().collect()
+    }
+}
+
 impl Reward {
+    /// Sums up the amounts of all the given rewards by their type. This makes
+    /// it easy to compare different reward chests
+    #[must_use]
+    pub fn aggregate(rewards: &[Reward]) -> RewardTotals {
+        let mut totals = RewardTotals::default();
+        for reward in rewards {
+            let total = totals.amounts.entry(reward.typ).or_default();
+            *total = total.saturating_add(reward.amount);
+        }
+        totals
+    }
+
     pub(crate) fn parse(data: &[i64]) -> Result<Reward, SFError> {
         Ok(Reward {
             typ: RewardType::parse(data.cget(0, "reward typ")?),
@@ -753,6 +808,12 @@ impl Reward {
 }
 
 impl RewardChest {
+    /// Sums up all rewards in this chest by their type
+    #[must_use]
+    pub fn totals(&self) -> RewardTotals {
+        Reward::aggregate(&self.rewards)
+    }
+
     pub(crate) fn parse(data: &[i64]) -> Result<RewardChest, SFError> {
         let opened = data.cget(0, "rchest opened")? != 0;
         let required_points = data.ciget(1, "reward chest required points")?;
@@ -811,3 +872,49 @@ pub(crate) fn parse_rewards(vals: &[i64]) -> [RewardChest; 3] {
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn totals() -> RewardTotals {
+        let reward = |typ, amount| Reward { typ, amount };
+        Reward::aggregate(&[
+            reward(RewardType::Silver, 100),
+            reward(RewardType::Fruit(HabitatType::Water), 3),
+            reward(RewardType::Mushrooms, 2),
+            reward(RewardType::Silver, 50),
+            reward(RewardType::Fruit(HabitatType::Fire), 1),
+            reward(RewardType::Mushrooms, u64::MAX),
+        ])
+    }
+
+    #[test]
+    fn aggregate_sums_by_type() {
+        let totals = totals();
+        assert_eq!(totals.get(RewardType::Silver), 150);
+        assert_eq!(totals.get(RewardType::Mushrooms), u64::MAX);
+        assert_eq!(totals.get(RewardType::Fruit(HabitatType::Water)), 3);
+        assert_eq!(totals.get(RewardType::Wood), 0);
+
+        let types: Vec<_> = totals.iter().map(|(typ, _)| typ).collect();
+        assert_eq!(
+            types,
+            vec![
+                RewardType::Mushrooms,
+                RewardType::Silver,
+                RewardType::Fruit(HabitatType::Fire),
+                RewardType::Fruit(HabitatType::Water),
+            ]
+        );
+    }
+
+    #[cfg(feature = "sso")]
+    #[test]
+    fn totals_json_round_trip() {
+        let totals = totals();
+        let json = serde_json::to_string(&totals).ok();
+        let parsed = json.and_then(|json| serde_json::from_str(&json).ok());
+        assert_eq!(parsed, Some(totals));
+    }
+}
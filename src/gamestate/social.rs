@@ -16,7 +16,7 @@ use super::{
     AttributeType, Class, Emblem, Flag, Item, Potion, Race, Reward, SFError,
     ServerTime,
 };
-use crate::{misc::*, PlayerId};
+use crate::{command::Command, misc::*, PlayerId};
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -37,6 +37,52 @@ pub struct Mail {
     pub open_claimable: Option<ClaimablePreview>,
 }
 
+impl Mail {
+    /// Appends the given combat log entries to the combat log, skipping any
+    /// entry, that is already in there. Two entries are considered equal, if
+    /// they happened at the same time against the same player.
+    ///
+    /// Fetching the combat log always replaces `combat_log` with the newly
+    /// fetched page. If you want to build up the full history across multiple
+    /// pages/polls, keep a separate `Mail` around and extend it with the
+    /// `combat_log` of the game state after each fetch. The resulting log is
+    /// sorted from newest to oldest
+    pub fn extend_combat_log(
+        &mut self,
+        entries: impl IntoIterator<Item = CombatLogEntry>,
+    ) {
+        for entry in entries {
+            let is_known = self.combat_log.iter().any(|a| {
+                a.time == entry.time && a.player_name == entry.player_name
+            });
+            if !is_known {
+                self.combat_log.push(entry);
+            }
+        }
+        self.combat_log.sort_by_key(|a| std::cmp::Reverse(a.time));
+    }
+
+    /// Finds the claimable with the given `msg_id`. Note that the response to
+    /// `Command::ClaimablePreview` does not contain the `msg_id`, so the
+    /// preview in `open_claimable` always belongs to the claimable, that the
+    /// last command was send for
+    #[must_use]
+    pub fn claimable(&self, msg_id: i64) -> Option<&ClaimableMail> {
+        self.claimables.iter().find(|a| a.msg_id == msg_id)
+    }
+
+    /// Finds the position of the message with the given id in the inbox. This
+    /// position is what `Command::MessageOpen` and `Command::MessageDelete`
+    /// expect
+    #[must_use]
+    pub fn inbox_pos(&self, msg_id: i32) -> Option<i32> {
+        self.inbox
+            .iter()
+            .position(|a| a.msg_id == msg_id)
+            .and_then(|a| a.try_into().ok())
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Contains information about everything involving other players on the server.
@@ -75,6 +121,39 @@ pub struct HallOfFames {
     pub underworlds: Vec<HallOfFameUnderworld>,
 }
 
+/// The different Hall of Fames, that can be queried on a server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HofKind {
+    Players,
+    Guilds,
+    Fortresses,
+    Pets,
+    Underworlds,
+    Hellevator,
+}
+
+impl HofKind {
+    /// The command to fetch the given page (51 entries each) of this Hall of
+    /// Fame
+    #[must_use]
+    pub fn page_command(self, page: usize) -> Command {
+        let small_page = || page.try_into().unwrap_or(u32::MAX);
+        match self {
+            HofKind::Players => Command::HallOfFamePage { page },
+            HofKind::Guilds => {
+                Command::HallOfFameGroupPage { page: small_page() }
+            }
+            HofKind::Fortresses => Command::HallOfFameFortressPage { page },
+            HofKind::Pets => Command::HallOfFamePetsPage { page: small_page() },
+            HofKind::Underworlds => {
+                Command::HallOfFameUnderworldPage { page: small_page() }
+            }
+            HofKind::Hellevator => Command::HallOfFameHellevatorPage { page },
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HallOfFameHellevator {
@@ -93,7 +172,9 @@ pub struct Lookup {
     players: HashMap<PlayerId, OtherPlayer>,
     name_to_id: HashMap<String, PlayerId>,
 
-    /// Guild that the character has looked at
+    /// Guild that the character has looked at. The iteration order of this
+    /// map is not stable. Use `guilds_sorted()`, if you need a consistent
+    /// order
     pub guilds: HashMap<String, OtherGuild>,
 }
 
@@ -138,6 +219,37 @@ impl Lookup {
         self.players = HashMap::default();
         self.name_to_id = HashMap::default();
     }
+
+    /// Returns all players, that have previously been queried, sorted by
+    /// their player id. Unlike iterating over the underlying map, this
+    /// order is stable
+    #[must_use]
+    pub fn players_sorted(&self) -> Vec<&OtherPlayer> {
+        let mut players: Vec<_> = self.players.values().collect();
+        players.sort_by_key(|a| a.player_id);
+        players
+    }
+
+    /// Finds a guild, that has previously been queried. The ident can be
+    /// either the name, or the id of the guild, just like the `guild_ident`
+    /// in `Command::ViewGuild`
+    #[must_use]
+    pub fn guild_by_ident(&self, ident: &str) -> Option<&OtherGuild> {
+        if let Some(guild) = self.guilds.get(ident) {
+            return Some(guild);
+        }
+        let id: u32 = ident.parse().ok()?;
+        self.guilds.values().find(|a| a.id == id)
+    }
+
+    /// Returns all guilds, that have previously been queried, sorted by their
+    /// name. Unlike iterating over `guilds`, this order is stable
+    #[must_use]
+    pub fn guilds_sorted(&self) -> Vec<&OtherGuild> {
+        let mut guilds: Vec<_> = self.guilds.values().collect();
+        guilds.sort_by(|a, b| a.name.cmp(&b.name));
+        guilds
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -356,6 +468,45 @@ pub struct HallOfFameUnderworld {
     pub unknown: i64,
 }
 
+/// The common information every entry in any of the Hall of Fames has. This
+/// makes it possible to handle all of them the same way
+pub trait HallOfFameEntry {
+    /// The rank of this entry in its Hall of Fame
+    fn rank(&self) -> u32;
+    /// The name of the player/guild, that this entry belongs to
+    fn name(&self) -> &str;
+}
+
+macro_rules! impl_hall_of_fame_entry {
+    ($($t:ty),+) => {$(
+        impl HallOfFameEntry for $t {
+            fn rank(&self) -> u32 {
+                self.rank
+            }
+            fn name(&self) -> &str {
+                &self.name
+            }
+        }
+    )+};
+}
+
+impl_hall_of_fame_entry!(
+    HallOfFamePlayer,
+    HallOfFameGuild,
+    HallOfFameFortress,
+    HallOfFamePets,
+    HallOfFameUnderworld
+);
+
+impl HallOfFameEntry for HallOfFameHellevator {
+    fn rank(&self) -> u32 {
+        self.rank.try_into().unwrap_or(u32::MAX)
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// All information about another player, that was queried via the `ViewPlayer`
@@ -410,6 +561,8 @@ pub struct OtherPlayer {
     pub armor: u64,
     pub min_damage_base: u32,
     pub max_damage_base: u32,
+    /// The amount of soldiers the game recommends to attack the fortress of
+    /// this player with. See `recommended_attack_soldiers()`
     pub soldier_advice: Option<u16>,
     pub fortress: Option<OtherFortress>,
 }
@@ -445,6 +598,15 @@ pub enum Relationship {
 }
 
 impl OtherPlayer {
+    /// The amount of soldiers the game recommends to use to win an attack on
+    /// the fortress of this player. This can directly be used as the
+    /// `soldiers` in `Command::FortressAttack`. This is only available, if
+    /// the server has send the advice alongside this player
+    #[must_use]
+    pub fn recommended_attack_soldiers(&self) -> Option<u32> {
+        self.soldier_advice.map(u32::from)
+    }
+
     pub(crate) fn update_pet_bonus(
         &mut self,
         data: &[u32],
@@ -576,14 +738,28 @@ pub enum CombatMessageType {
     GuildFightWon = 27,
 }
 
-#[derive(Debug, Clone, FromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The kind of a message in the inbox. Fight reports are not part of the
+/// inbox and can be found in the combat log instead
 pub enum MessageType {
+    /// A message written by another player
     Normal,
+    /// An invitation to join a guild
     GuildInvite,
+    /// A notification, that you have been kicked from your guild
     GuildKicked,
 }
 
+impl MessageType {
+    /// Checks if this message was send by the system because of something
+    /// related to guilds, instead of being written by a player
+    #[must_use]
+    pub fn is_guild_related(self) -> bool {
+        matches!(self, MessageType::GuildInvite | MessageType::GuildKicked)
+    }
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CombatLogEntry {
@@ -633,6 +809,20 @@ pub struct InboxEntry {
 }
 
 impl InboxEntry {
+    /// The kind of message this is
+    #[must_use]
+    pub fn kind(&self) -> MessageType {
+        self.msg_typ
+    }
+
+    /// The id of this message. This stays the same, even if the position of
+    /// the message in the inbox changes. Use `Mail::inbox_pos()` to get the
+    /// position you need for `Command::MessageOpen`
+    #[must_use]
+    pub fn id(&self) -> i32 {
+        self.msg_id
+    }
+
     pub(crate) fn parse(
         msg: &str,
         server_time: ServerTime,
@@ -681,6 +871,8 @@ impl InboxEntry {
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OtherGuild {
+    /// The internal server id of this guild
+    pub id: u32,
     pub name: String,
 
     pub attacks: Option<String>,
@@ -694,6 +886,7 @@ pub struct OtherGuild {
     pub finished_raids: u16,
     // should just be members.len(), right?
     member_count: u8,
+    /// The members of this guild, alongside their level, rank, etc.
     pub members: Vec<OtherGuildMember>,
 }
 
@@ -709,6 +902,24 @@ pub struct OtherGuildMember {
     pub last_active: Option<DateTime<Local>>,
 }
 impl OtherGuild {
+    /// The amount of members this guild has
+    #[must_use]
+    pub fn member_count(&self) -> usize {
+        self.members.len()
+    }
+
+    /// The average level of all members in this guild. If the guild has no
+    /// members, this will be 0
+    #[must_use]
+    pub fn average_member_level(&self) -> f64 {
+        let count = u32::try_from(self.members.len()).unwrap_or(u32::MAX);
+        if count == 0 {
+            return 0.0;
+        }
+        let total: u32 = self.members.iter().map(|a| u32::from(a.level)).sum();
+        f64::from(total) / f64::from(count)
+    }
+
     pub(crate) fn update(
         &mut self,
         val: &str,
@@ -719,6 +930,7 @@ impl OtherGuild {
             .map(|c| c.trim().parse::<i64>().unwrap_or_default())
             .collect();
 
+        self.id = data.csiget(0, "other guild id", 0)?;
         self.member_count = data.csiget(3, "member count", 0)?;
         let member_count = self.member_count as usize;
         self.finished_raids = data.csiget(8, "raid count", 0)?;
@@ -769,6 +981,24 @@ pub struct ClaimableMail {
     pub claimable_until: Option<DateTime<Local>>,
 }
 
+impl ClaimableMail {
+    /// Checks if the time to claim this has run out. If there is no time
+    /// limit, this will never expire
+    #[must_use]
+    pub fn is_expired(&self, server_time: ServerTime) -> bool {
+        self.claimable_until
+            .is_some_and(|a| server_time.has_passed(a))
+    }
+
+    /// Checks if this has not been claimed yet and is not expired. If this is
+    /// true, you can preview the rewards via `Command::ClaimablePreview` and
+    /// then claim them via `Command::ClaimableClaim`
+    #[must_use]
+    pub fn can_claim(&self, server_time: ServerTime) -> bool {
+        self.status != ClaimableStatus::Claimed && !self.is_expired(server_time)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ClaimableStatus {
@@ -793,3 +1023,24 @@ pub struct ClaimablePreview {
     pub items: Vec<Item>,
     pub resources: Vec<Reward>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recommended_attack_soldiers() {
+        let mut player = OtherPlayer::default();
+        assert_eq!(player.recommended_attack_soldiers(), None);
+
+        player.soldier_advice = Some(37);
+        let soldiers = player.recommended_attack_soldiers();
+        assert_eq!(soldiers, Some(37));
+        let command =
+            soldiers.map(|soldiers| Command::FortressAttack { soldiers });
+        assert_eq!(
+            command.and_then(|c| c.request_string().ok()).as_deref(),
+            Some("FortressAttack:37")
+        );
+    }
+}
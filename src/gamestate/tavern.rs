@@ -4,11 +4,13 @@ use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 
 use super::{
-    items::Item, ArrSkip, CCGet, CFPGet, CGet, CSTGet, ExpeditionSetting,
-    SFError, ServerTime,
+    character::Character,
+    items::{Enchantment, Item},
+    ArrSkip, CCGet, CFPGet, CGet, CSTGet, ExpeditionSetting, SFError,
+    ServerTime,
 };
 use crate::{
-    command::{DiceReward, DiceType},
+    command::{DiceReward, DiceType, TimeSkip},
     gamestate::rewards::Reward,
     misc::soft_into,
 };
@@ -148,6 +150,42 @@ impl Tavern {
         self.thirst_for_adventure_sec == 6000 && self.beer_drunk == 0
     }
 
+    /// The maximum amount of beers the character can drink today. This is 10
+    /// for everyone, with one extra beer, if the character has the
+    /// `ThirstyWanderer` enchantment equipped
+    #[must_use]
+    pub fn max_beers_today(&self, character: &Character) -> u8 {
+        let has_extra_beer = character
+            .equipment
+            .has_enchantment(Enchantment::ThirstyWanderer);
+        10 + u8::from(has_extra_beer)
+    }
+
+    /// Checks if the character has the mushrooms and has not yet reached the
+    /// daily limit, that is required to buy another beer
+    #[must_use]
+    pub fn can_buy_beer(&self, character: &Character) -> bool {
+        character.can_afford_mushrooms(1)
+            && self.beer_drunk < self.max_beers_today(character)
+    }
+
+    /// Ranks the available quests by the given priority and returns the index
+    /// of the best one. This index can directly be used as the `quest_pos` in
+    /// `StartQuest`. Note that this only looks at the base values, so
+    /// enchantments, runes, or events are not taken into account
+    #[must_use]
+    pub fn best_quest(&self, priority: QuestPriority) -> Option<usize> {
+        self.quests
+            .iter()
+            .enumerate()
+            .filter(|(_, q)| q.base_length > 0)
+            .max_by(|(_, a), (_, b)| {
+                a.priority_score(priority)
+                    .total_cmp(&b.priority_score(priority))
+            })
+            .map(|(idx, _)| idx)
+    }
+
     pub(crate) fn update(
         &mut self,
         data: &[i64],
@@ -187,6 +225,30 @@ pub struct Quest {
     pub monster_id: u16,
 }
 
+/// The rewards you would get for finishing a quest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuestReward<'a> {
+    /// The silver reward for this quest (without item enchantment)
+    pub silver: u32,
+    /// The xp reward for this quest (without item enchantment)
+    pub experience: u32,
+    /// The item reward for this quest, if there is one
+    pub item: Option<&'a Item>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The thing you value most, when picking the next quest to do
+pub enum QuestPriority {
+    /// Picks the quest with the most experience per second
+    #[default]
+    Experience,
+    /// Picks the quest with the most silver per second
+    Silver,
+    /// Picks the shortest quest
+    Duration,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Copy, FromPrimitive, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
@@ -224,6 +286,29 @@ impl Quest {
         matches!(self.monster_id, 139 | 145 | 148 | 152 | 155 | 157)
     }
 
+    /// Gives you a summary of everything you would get for finishing this
+    /// quest
+    #[must_use]
+    pub fn reward_summary(&self) -> QuestReward<'_> {
+        QuestReward {
+            silver: self.base_silver,
+            experience: self.base_experience,
+            item: self.item.as_ref(),
+        }
+    }
+
+    /// The score of this quest for the given priority. Higher is better
+    fn priority_score(&self, priority: QuestPriority) -> f64 {
+        let length = f64::from(self.base_length.max(1));
+        match priority {
+            QuestPriority::Experience => {
+                f64::from(self.base_experience) / length
+            }
+            QuestPriority::Silver => f64::from(self.base_silver) / length,
+            QuestPriority::Duration => -length,
+        }
+    }
+
     pub(crate) fn parse(
         data: &[i64],
         quest_index: usize,
@@ -338,8 +423,15 @@ pub struct Expedition {
 
     /// The level we are currently clearing. Starts at 1
     pub current_floor: u8,
-    ///  The heroism we have collected so far
+    /// The heroism we have collected so far. Heroism is the score of an
+    /// expedition and decides the rewards you get at the end of it. Every
+    /// encounter you pick adds its heroism to this
     pub heroism: i32,
+    /// All the raw values of items and encounters, that were parsed as
+    /// `ExpeditionThing::Unknown` so far. Each value is only stored once. If
+    /// this is not empty, please report these values, so that they can be
+    /// added
+    pub unknown_values: Vec<i64>,
 
     pub(crate) adjusted_bounty_heroism: bool,
 
@@ -356,6 +448,30 @@ pub struct Expedition {
 }
 
 impl Expedition {
+    /// The heroism we have collected so far. See the `heroism` field for more
+    /// information
+    #[must_use]
+    pub fn heroism(&self) -> i64 {
+        i64::from(self.heroism)
+    }
+
+    /// Returns the bounty, that we have collected in this expedition, if any.
+    /// Picking an encounter, that this bounty is for, gives an extra 10
+    /// heroism. This bonus is already included in the heroism of the
+    /// encounters in `current_stage()`
+    #[must_use]
+    pub fn current_bounty(&self) -> Option<ExpeditionThing> {
+        self.items
+            .iter()
+            .flatten()
+            .copied()
+            .find(|a| a.is_bounty_for().is_some())
+    }
+
+    /// The server sends us the heroism of each encounter without the bonus
+    /// from bounties. To make the encounter heroism match what the player
+    /// would get, we add the +10 bonus for every encounter, that we have the
+    /// bounty for. This is only done once per set of encounters
     pub(crate) fn adjust_bounty_heroism(&mut self) {
         if self.adjusted_bounty_heroism {
             return;
@@ -375,16 +491,20 @@ impl Expedition {
         if data.len() % 2 != 0 {
             warn!("weird encounters: {data:?}");
         }
-        let default_ecp = |ci| {
-            warn!("Unknown encounter: {ci}");
-            ExpeditionThing::Unknown
-        };
+        // These are new encounters, so they have to be adjusted again
+        self.adjusted_bounty_heroism = false;
+        let unknown_values = &mut self.unknown_values;
         self.encounters = data
             .chunks_exact(2)
             .filter_map(|ci| {
                 let raw = *ci.first()?;
-                let typ = FromPrimitive::from_i64(raw)
-                    .unwrap_or_else(|| default_ecp(raw));
+                let typ = FromPrimitive::from_i64(raw).unwrap_or_else(|| {
+                    warn!("Unknown encounter: {raw}");
+                    if !unknown_values.contains(&raw) {
+                        unknown_values.push(raw);
+                    }
+                    ExpeditionThing::Unknown
+                });
                 let heroism = soft_into(*ci.get(1)?, "e heroism", 0);
                 Some(ExpeditionEncounter { typ, heroism })
             })
@@ -394,7 +514,9 @@ impl Expedition {
     #[must_use]
     /// Returns the current stage the player is doing. This is dependent on
     /// time, because the timers are lazily evaluated. That means it might
-    /// flip from Waiting->Encounters/Finished between calls
+    /// flip from Waiting->Encounters/Finished between calls. Because of that,
+    /// the result is intentionally not cached. Computing it is cheap anyways,
+    /// as everything has already been parsed during the update
     pub fn current_stage(&self) -> ExpeditionStage {
         let cross_roads =
             || ExpeditionStage::Encounters(self.encounters.clone());
@@ -412,6 +534,31 @@ impl Expedition {
         }
     }
 
+    #[must_use]
+    /// Returns the amount of mushrooms, that `Command::ExpeditionSkipWait`
+    /// would cost with the given currency. Returns `None`, if the expedition
+    /// is not currently waiting, so there is nothing to skip.
+    ///
+    /// The server does not send this price. The game always charges exactly
+    /// one of the chosen currency for skipping an expedition wait, no matter
+    /// how long the wait is. That is one mushroom, or one quicksand glass,
+    /// which costs no mushrooms
+    pub fn wait_skip_cost(
+        &self,
+        typ: TimeSkip,
+        server_time: ServerTime,
+    ) -> Option<u32> {
+        let waiting = self.floor_stage == 4
+            && self.busy_until.is_some_and(|a| !server_time.has_passed(a));
+        if !waiting {
+            return None;
+        }
+        Some(match typ {
+            TimeSkip::Mushroom => 1,
+            TimeSkip::Glass => 0,
+        })
+    }
+
     #[must_use]
     /// Checks, if the last timer of this expedition has run out
     pub fn is_finished(&self) -> bool {
@@ -572,6 +719,26 @@ impl ExpeditionThing {
         })
     }
 
+    #[must_use]
+    #[allow(clippy::enum_glob_use)]
+    /// Classifies this thing by the value it has, when picking it at a
+    /// crossroad. This is only a hint to rank encounters, as the actual value
+    /// depends on the items you already have
+    pub fn value_hint(&self) -> ExpeditionValue {
+        use ExpeditionThing::*;
+        if self.is_bounty_for().is_some() {
+            return ExpeditionValue::Bounty;
+        }
+        if self.required_bounty().is_some() {
+            return ExpeditionValue::Heroism;
+        }
+        match self {
+            Unknown => ExpeditionValue::Unknown,
+            Key | Suitcase => ExpeditionValue::Resource,
+            _ => ExpeditionValue::Item,
+        }
+    }
+
     #[must_use]
     #[allow(clippy::enum_glob_use)]
     /// If the thing is a bounty, this will contain all the things, that receive
@@ -595,6 +762,25 @@ impl ExpeditionThing {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A rough classification of what picking an `ExpeditionThing` at a crossroad
+/// will give you
+pub enum ExpeditionValue {
+    /// A part, that has to be combined with other parts to become something,
+    /// that is worth heroism
+    Item,
+    /// Something, that directly gives heroism. A matching bounty in the items
+    /// increases the heroism of these
+    Heroism,
+    /// A bounty, that increases the heroism of the things it is for
+    Bounty,
+    /// Something, that gives extra loot/resources instead of heroism
+    Resource,
+    /// We do not know what this thing is
+    Unknown,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Information about a possible expedition, that you could start
@@ -621,3 +807,73 @@ pub enum GambleResult {
     SilverChange(i64),
     MushroomChange(i32),
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn wait_skip_cost_only_while_waiting() {
+        let server_time = ServerTime::default();
+        let mut expedition = Expedition {
+            floor_stage: 4,
+            busy_until: Some(Local::now() + Duration::minutes(10)),
+            ..Default::default()
+        };
+        assert_eq!(
+            expedition.wait_skip_cost(TimeSkip::Mushroom, server_time),
+            Some(1)
+        );
+        assert_eq!(
+            expedition.wait_skip_cost(TimeSkip::Glass, server_time),
+            Some(0)
+        );
+
+        expedition.busy_until = Some(Local::now() - Duration::minutes(10));
+        assert_eq!(
+            expedition.wait_skip_cost(TimeSkip::Mushroom, server_time),
+            None
+        );
+        expedition.floor_stage = 1;
+        expedition.busy_until = Some(Local::now() + Duration::minutes(10));
+        assert_eq!(
+            expedition.wait_skip_cost(TimeSkip::Glass, server_time),
+            None
+        );
+    }
+
+    #[test]
+    fn beer_limit_with_and_without_enchantment() {
+        use crate::{
+            gamestate::items::{EquipmentSlot, ItemType},
+            misc::EnumMapGet,
+        };
+
+        let mut character = Character {
+            mushrooms: 5,
+            ..Default::default()
+        };
+        let mut tavern = Tavern {
+            beer_drunk: 9,
+            ..Default::default()
+        };
+        assert_eq!(tavern.max_beers_today(&character), 10);
+        assert!(tavern.can_buy_beer(&character));
+        tavern.beer_drunk = 10;
+        assert!(!tavern.can_buy_beer(&character));
+
+        let mut belt = Item::new(ItemType::Belt);
+        belt.enchantment = Some(Enchantment::ThirstyWanderer);
+        *character.equipment.0.get_mut(EquipmentSlot::Belt) = Some(belt);
+        assert_eq!(tavern.max_beers_today(&character), 11);
+        assert!(tavern.can_buy_beer(&character));
+        tavern.beer_drunk = 11;
+        assert!(!tavern.can_buy_beer(&character));
+
+        tavern.beer_drunk = 0;
+        character.mushrooms = 0;
+        assert!(!tavern.can_buy_beer(&character));
+    }
+}
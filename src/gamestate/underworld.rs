@@ -1,7 +1,7 @@
 #![allow(clippy::module_name_repetitions)]
 use std::time::Duration;
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate};
 use enum_map::{Enum, EnumMap};
 use num_derive::FromPrimitive;
 use strum::{EnumIter, IntoEnumIterator};
@@ -43,10 +43,18 @@ pub struct Underworld {
     /// The combined level of all buildings in the underworld, which is
     /// equivalent to honor
     pub honor: u16,
-    /// The amount of players, that have been lured into the underworld today
+    /// The amount of players, that have been lured into the underworld today.
+    /// At most `MAX_LURES_PER_DAY` players can be lured each day
     pub lured_today: u16,
+    /// The day (server time), that `lured_today` has been parsed on
+    pub(crate) lured_day: Option<NaiveDate>,
 }
 
+/// The maximum amount of players, that can be lured into the underworld per
+/// day. The server does not send this limit, so this is the value the games
+/// underworld help text states ("up to 5 heroes per day")
+pub const MAX_LURES_PER_DAY: u16 = 5;
+
 #[derive(Debug, Default, Clone, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The price an upgrade, or building something in the underworld costs. These
@@ -72,6 +80,77 @@ impl UnderworldCost {
 }
 
 impl Underworld {
+    /// Checks if another player can be lured into the underworld today via
+    /// `Command::UnderworldAttack`. The amount of lures resets at midnight
+    /// server time, so if the last update was on a previous day, this will be
+    /// true, even if `lured_today` is still at the limit
+    #[must_use]
+    pub fn can_lure(&self, server_time: ServerTime) -> bool {
+        self.lured_today < MAX_LURES_PER_DAY
+            || self.lured_day != Some(server_time.current().date())
+    }
+
+    /// The amount of seconds, that `per_hour` in the production of this
+    /// resource refers to
+    fn production_period_secs(res: UnderWorldResourceType) -> u64 {
+        match res {
+            // The adventuromatic production is per day, not per hour
+            UnderWorldResourceType::ThirstForAdventure => 24 * 60 * 60,
+            _ => 60 * 60,
+        }
+    }
+
+    /// Estimates the amount of the resource, that can currently be collected
+    /// from its building. `last_collectable` is only updated on requests, so
+    /// this adds the production since `last_collectable_update`
+    #[must_use]
+    pub fn collectable(&self, res: UnderWorldResourceType) -> u64 {
+        let production = self.production.get(res);
+        let elapsed = self
+            .last_collectable_update
+            .and_then(|a| (Local::now() - a).to_std().ok())
+            .unwrap_or_default()
+            .as_secs();
+        let produced = elapsed.saturating_mul(production.per_hour)
+            / Self::production_period_secs(res);
+        production
+            .last_collectable
+            .saturating_add(produced)
+            .min(production.limit)
+    }
+
+    /// Checks if there is anything to collect via `Command::UnderworldCollect`
+    /// for the given resource. For souls, this also requires, that the storage
+    /// of souls is not yet full
+    #[must_use]
+    pub fn can_collect(&self, res: UnderWorldResourceType) -> bool {
+        if res == UnderWorldResourceType::Souls
+            && self.souls_current >= self.souls_limit
+        {
+            return false;
+        }
+        self.collectable(res) > 0
+    }
+
+    /// Estimates the time until the building of the resource has reached its
+    /// limit and stops producing. If the building is already full, this will
+    /// be zero. If the building does not produce anything, this returns None
+    #[must_use]
+    pub fn time_until_full(
+        &self,
+        res: UnderWorldResourceType,
+    ) -> Option<Duration> {
+        let production = self.production.get(res);
+        if production.per_hour == 0 {
+            return None;
+        }
+        let missing = production.limit.saturating_sub(self.collectable(res));
+        let secs = missing
+            .saturating_mul(Self::production_period_secs(res))
+            .div_ceil(production.per_hour);
+        Some(Duration::from_secs(secs))
+    }
+
     pub(crate) fn update_building_prices(
         &mut self,
         data: &[i64],
@@ -156,6 +235,7 @@ impl Underworld {
             data.cstget(470, "u upgrade begin", server_time)?;
         self.honor = data.csiget(471, "uu honor", 0)?;
         self.lured_today = data.csiget(472, "u battles today", 0)?;
+        self.lured_day = Some(server_time.current().date());
         Ok(())
     }
 }
@@ -255,3 +335,28 @@ pub struct UnderworldUnit {
     /// The level this unit will have, when the upgrade has been bought
     pub upgrade_next_lvl: u16,
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn lures_reset_on_a_new_day() {
+        let server_time = ServerTime::default();
+        let today = server_time.current().date();
+        let mut underworld = Underworld {
+            lured_today: MAX_LURES_PER_DAY - 1,
+            lured_day: Some(today),
+            ..Default::default()
+        };
+        assert!(underworld.can_lure(server_time));
+
+        underworld.lured_today = MAX_LURES_PER_DAY;
+        assert!(!underworld.can_lure(server_time));
+
+        underworld.lured_day = Some(today - Duration::days(1));
+        assert!(underworld.can_lure(server_time));
+    }
+}
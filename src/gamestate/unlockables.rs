@@ -7,7 +7,14 @@ use num_derive::FromPrimitive;
 use strum::EnumIter;
 
 use super::*;
-use crate::{gamestate::items::*, misc::*, PlayerId};
+use crate::{
+    gamestate::items::*,
+    misc::*,
+    simulate::{
+        Battle, BattleFighter, BattleSide, Monster, PlayerFighterSquad,
+    },
+    PlayerId,
+};
 
 #[derive(Debug, Default, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -68,6 +75,17 @@ impl HellevatorEvent {
         }
     }
 
+    /// Checks if the final reward of the event can be claimed via
+    /// `Command::HellevatorClaimFinal`. The hellevator is not accessible
+    /// after the event has ended, so this just forwards to
+    /// `Hellevator::can_claim_final()`
+    #[must_use]
+    pub fn can_claim_final(&self, server_time: ServerTime) -> bool {
+        self.active
+            .as_ref()
+            .is_some_and(|h| h.can_claim_final(server_time))
+    }
+
     // /// If the Hellevator event is active, this returns a mutable reference
     // to /// the Information about it
     // #[must_use]
@@ -113,6 +131,11 @@ pub struct Hellevator {
     pub earned_yesterday: u32,
 
     pub(crate) brackets: Vec<u32>,
+
+    /// Copy of `HellevatorEvent::end`
+    pub(crate) event_end: Option<DateTime<Local>>,
+    /// Copy of `HellevatorEvent::collect_time_end`
+    pub(crate) collect_time_end: Option<DateTime<Local>>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -128,6 +151,11 @@ pub struct HellevatorMonster {
     pub id: i64,
     pub level: u32,
     pub typ: HellevatorMonsterElement,
+    /// The stats of the monster, that are needed to simulate a fight against
+    /// it. The server only sends the id, level and element, so this is never
+    /// set by the parser and has to be filled in by you
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub stats: Option<Monster>,
 }
 
 #[derive(Debug, Clone, Default, Copy, PartialEq, Eq, Hash, FromPrimitive)]
@@ -146,6 +174,7 @@ impl HellevatorMonster {
             id: data.cget(0, "h monster id")?,
             level: data.csiget(1, "h monster level", 0)?,
             typ: data.cfpget(2, "h monster typ", |a| a)?.unwrap_or_default(),
+            stats: None,
         })
     }
 }
@@ -250,6 +279,19 @@ pub struct HellevatorDailyReward {
 }
 
 impl HellevatorDailyReward {
+    /// Checks if this reward contains anything at all
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.gold_chests == 0
+            && self.fortress_chests == 0
+            && self.blacksmith_chests == 0
+            && self.silver == 0
+            && self.wood == 0
+            && self.stone == 0
+            && self.arcane == 0
+            && self.metal == 0
+    }
+
     pub(crate) fn parse(data: &[i64]) -> Option<HellevatorDailyReward> {
         if data.len() != 10 {
             return None;
@@ -269,6 +311,76 @@ impl HellevatorDailyReward {
 }
 
 impl Hellevator {
+    /// Checks if the reward for today contains anything, that could be
+    /// claimed via `Command::HellevatorClaimDaily`. This only looks at
+    /// `rewards_today`, so it does not know about a claim, that happened after
+    /// the last update
+    #[must_use]
+    pub fn can_claim_daily(&self) -> bool {
+        self.rewards_today.as_ref().is_some_and(|a| !a.is_empty())
+    }
+
+    /// Checks if the final reward of the event can be claimed via
+    /// `Command::HellevatorClaimFinal`. This is only possible after the event
+    /// has ended, but before the collection time is over and only once
+    #[must_use]
+    pub fn can_claim_final(&self, server_time: ServerTime) -> bool {
+        !self.has_final_reward
+            && self.event_end.is_some_and(|a| server_time.has_passed(a))
+            && self
+                .collect_time_end
+                .is_some_and(|a| !server_time.has_passed(a))
+    }
+
+    /// Sums up the rewards for beating the current monster by their type
+    #[must_use]
+    pub fn monster_reward_total(&self, typ: HellevatorMonsterRewardTyp) -> u64 {
+        self.monster_rewards
+            .iter()
+            .filter(|a| a.typ == typ)
+            .map(|a| a.amount)
+            .sum()
+    }
+
+    /// Simulates the fight of the squad against the current monster and
+    /// checks if the squad wins the majority of these fights. The server
+    /// does not send the stats of hellevator monsters, so this returns `None`,
+    /// if there is no current monster, or its `stats` have not been set
+    #[must_use]
+    pub fn can_defeat_current(
+        &self,
+        squad: &PlayerFighterSquad,
+    ) -> Option<bool> {
+        const SIMULATIONS: u32 = 1_000;
+
+        let monster = self.current_monster.as_ref()?.stats.as_ref()?;
+        let mut left = BattleFighter::from_squad(squad);
+        let mut right = [BattleFighter::from_monster(monster)];
+        let mut battle = Battle::new(&mut left, &mut right);
+        battle.rng = fastrand::Rng::with_seed(u64::from(monster.level));
+
+        let wins = (0..SIMULATIONS)
+            .filter(|_| battle.simulate(&mut ()) == BattleSide::Left)
+            .count();
+        Some(wins > SIMULATIONS as usize / 2)
+    }
+
+    /// Checks if the signup for the next guild raid is currently open, so
+    /// that `Command::HellevatorJoinHellAttack` can be send
+    #[must_use]
+    pub fn can_signup_raid(&self, server_time: ServerTime) -> bool {
+        server_time.has_passed(self.guild_raid_signup_start)
+            && !server_time.has_passed(self.guild_raid_start)
+    }
+
+    /// All the floors of the guild raid, that can be chosen when signing up.
+    /// The index of a floor in here is the `plain` value
+    /// `Command::HellevatorJoinHellAttack` expects
+    #[must_use]
+    pub fn raid_floor_options(&self) -> &[HellevatorRaidFloor] {
+        &self.guild_raid_floors
+    }
+
     /// Converts the rank of a guild in the Hellevator into the reward bracket,
     /// that they would be in (1 to 25). If the rank would gain no rewards, none
     /// is returned here
@@ -599,7 +711,19 @@ pub struct PetStats {
     pub element: HabitatType,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Enum, EnumIter, Hash)]
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Enum,
+    EnumIter,
+    Hash,
+    PartialOrd,
+    Ord,
+)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HabitatType {
     #[default]
@@ -705,6 +829,21 @@ impl Mirror {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+/// A part of the game, that is only available after it has been unlocked. See
+/// `GameState::newly_unlocked_features()`
+pub enum Feature {
+    Fortress,
+    Underworld,
+    Witch,
+    Portal,
+    Pets,
+    Blacksmith,
+    IdleGame,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Unlockable {
@@ -715,6 +854,43 @@ pub struct Unlockable {
 }
 
 impl Unlockable {
+    /// A short human-readable name of the thing, that would be unlocked. The
+    /// idents match the ids of the (unique) items, that unlock these
+    /// features, so everything, that is not known will just be "Unknown"
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match (self.main_ident, self.sub_ident & 0xFFFF) {
+            (11, 1..=11 | 17 | 19 | 22 | 69 | 70) => "Dungeon",
+            (11, 51..=64 | 67..=68) => "Shadow Dungeon",
+            (11, 20) => "Toilet",
+            (11, 10000) => "Epic Item Bag",
+            (13, _) => "Scrapbook",
+            (16, _) => "Pet",
+            (17, 4) => "Gral",
+            (18, _) => "Underworld",
+            (19, _) => "Wheel of Fortune",
+            (20, _) => "Mannequin",
+            _ => "Unknown",
+        }
+    }
+
+    /// A human-readable description of what this unlocks. In comparison to
+    /// `name()`, this also includes the sub ident, which for example is the
+    /// dungeon, that a key unlocks
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let name = self.name();
+        match name {
+            "Dungeon" | "Shadow Dungeon" => {
+                format!("{name} (key {})", self.sub_ident & 0xFFFF)
+            }
+            "Unknown" => {
+                format!("{name} ({}/{})", self.main_ident, self.sub_ident)
+            }
+            _ => name.to_string(),
+        }
+    }
+
     pub(crate) fn parse(data: &[i64]) -> Result<Vec<Unlockable>, SFError> {
         data.chunks_exact(2)
             .filter(|chunk| chunk.first().copied().unwrap_or_default() != 0)
@@ -942,3 +1118,84 @@ fn parse_scrapbook_item(index: i64) -> Option<EquipmentIdent> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn final_reward_only_claimable_after_the_event() {
+        let server_time = ServerTime::default();
+        let now = Local::now();
+        let mut hellevator = Hellevator {
+            event_end: Some(now + Duration::hours(1)),
+            collect_time_end: Some(now + Duration::days(1)),
+            ..Default::default()
+        };
+        assert!(!hellevator.can_claim_final(server_time));
+
+        hellevator.event_end = Some(now - Duration::hours(1));
+        assert!(hellevator.can_claim_final(server_time));
+
+        hellevator.has_final_reward = true;
+        assert!(!hellevator.can_claim_final(server_time));
+
+        hellevator.has_final_reward = false;
+        hellevator.collect_time_end = Some(now - Duration::minutes(1));
+        assert!(!hellevator.can_claim_final(server_time));
+    }
+
+    #[test]
+    fn raid_signup_window() {
+        let server_time = ServerTime::default();
+        let now = Local::now();
+        let mut hellevator = Hellevator {
+            guild_raid_signup_start: now - Duration::hours(1),
+            guild_raid_start: now + Duration::hours(1),
+            ..Default::default()
+        };
+        assert!(hellevator.can_signup_raid(server_time));
+
+        hellevator.guild_raid_start = now - Duration::minutes(1);
+        assert!(!hellevator.can_signup_raid(server_time));
+
+        hellevator.guild_raid_signup_start = now + Duration::hours(1);
+        hellevator.guild_raid_start = now + Duration::hours(2);
+        assert!(!hellevator.can_signup_raid(server_time));
+    }
+
+    #[test]
+    fn hellevator_fight_preview() {
+        let mut gs = GameState::default();
+        gs.character.level = 100;
+        gs.character.class = Class::Warrior;
+        gs.character.attribute_basis = EnumMap::from_array([2000; 5]);
+        let squad = PlayerFighterSquad::new(&gs);
+
+        let mut hellevator = Hellevator::default();
+        assert_eq!(hellevator.can_defeat_current(&squad), None);
+
+        let monster = HellevatorMonster {
+            id: -1,
+            level: 100,
+            typ: HellevatorMonsterElement::Fire,
+            stats: None,
+        };
+        hellevator.current_monster = Some(monster);
+        // The server never sends the stats, so we can not simulate this
+        assert_eq!(hellevator.can_defeat_current(&squad), None);
+
+        let set_stats = |h: &mut Hellevator, attr, hp| {
+            if let Some(monster) = &mut h.current_monster {
+                monster.stats =
+                    Some(Monster::new(100, Class::Mage, [attr; 5], hp, 0));
+            }
+        };
+        set_stats(&mut hellevator, 100, 1_000);
+        assert_eq!(hellevator.can_defeat_current(&squad), Some(true));
+        set_stats(&mut hellevator, 50_000, 100_000_000);
+        assert_eq!(hellevator.can_defeat_current(&squad), Some(false));
+    }
+}
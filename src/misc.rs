@@ -79,6 +79,18 @@ where
     })
 }
 
+/// Converts a raw server value into an enum. If the value is not known, a
+/// warning, that mentions `context` is logged and `None` is returned. This is
+/// the same way this crate parses enums, so you can use it to parse the
+/// responses of `Command::Custom` without panicking on new values
+#[must_use]
+pub fn parse_enum_or_warn<T: FromPrimitive>(
+    val: i64,
+    context: &str,
+) -> Option<T> {
+    warning_parse(val, context, FromPrimitive::from_i64)
+}
+
 #[inline]
 pub(crate) fn warning_from_str<T: FromStr>(val: &str, name: &str) -> Option<T> {
     val.parse().ok().or_else(|| {
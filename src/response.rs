@@ -142,6 +142,29 @@ impl Response {
         self.with_received_at(|a| *a)
     }
 
+    /// Checks if the server explicitly said, that the request was successful.
+    /// The server sends this either as `Success`, or misspelled as `sucess`.
+    /// Note that this is not reliable. The server sometimes sends this for bad
+    /// requests and many successful requests do not contain it at all, so
+    /// this should only be used for logging/debugging
+    #[must_use]
+    pub fn indicated_success(&self) -> bool {
+        let values = self.values();
+        if ["Success", "success", "Sucess", "sucess"]
+            .iter()
+            .any(|a| values.contains_key(a))
+        {
+            return true;
+        }
+        let body = self
+            .raw_response()
+            .trim_start_matches(|a: char| !a.is_alphabetic());
+        ["success", "sucess"].iter().any(|word| {
+            body.get(..word.len())
+                .is_some_and(|p| p.eq_ignore_ascii_case(word))
+        })
+    }
+
     /// Parses a response body from the server into a usable format
     /// You might want to use this, if you are analyzing responses from the
     /// browsers network tab. If you are trying to store/read responses to/from
@@ -311,3 +334,36 @@ impl std::fmt::Display for ResponseVal<'_> {
         f.write_str(self.value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Local;
+
+    use super::*;
+
+    fn parse(body: &str) -> Result<Response, SFError> {
+        Response::parse(body.to_string(), Local::now().naive_local())
+    }
+
+    #[test]
+    fn success_is_detected_in_both_spellings() {
+        for body in ["Success:", "sucess:", "SUCCESS:&timestamp:1"] {
+            let resp = parse(body);
+            assert!(
+                resp.as_ref().is_ok_and(Response::indicated_success),
+                "{body}"
+            );
+        }
+    }
+
+    #[test]
+    fn other_bodies_do_not_indicate_success() {
+        let resp = parse("timestamp:1&ownplayername.r:name");
+        assert!(resp.is_ok_and(|r| !r.indicated_success()));
+
+        let resp = parse("Error:need more gold");
+        assert!(
+            matches!(resp, Err(SFError::ServerError(msg)) if msg == "need more gold")
+        );
+    }
+}
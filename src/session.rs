@@ -1,8 +1,9 @@
 use std::{
     borrow::Borrow,
     fmt::Debug,
+    ops::Range,
     sync::{atomic::AtomicU32, Arc},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use log::{error, trace, warn};
@@ -14,6 +15,7 @@ use crate::{
     error::SFError,
     gamestate::{
         character::{Class, Gender, Race},
+        social::{HallOfFames, HofKind},
         GameState,
     },
     misc::{
@@ -536,6 +538,9 @@ impl Default for ConnectionOptions {
 pub struct SimpleSession {
     session: Session,
     gamestate: Option<GameState>,
+    min_request_interval: Duration,
+    last_request: Option<Instant>,
+    last_response: Option<String>,
 }
 
 impl SimpleSession {
@@ -562,6 +567,9 @@ impl SimpleSession {
         Ok(Self {
             session,
             gamestate: Some(gs),
+            min_request_interval: Duration::ZERO,
+            last_request: Some(Instant::now()),
+            last_response: None,
         })
     }
 
@@ -589,6 +597,9 @@ impl SimpleSession {
             .map(|a| Self {
                 session: a,
                 gamestate: None,
+                min_request_interval: Duration::ZERO,
+                last_request: None,
+                last_response: None,
             })
             .collect())
     }
@@ -607,6 +618,38 @@ impl SimpleSession {
         self.gamestate.as_mut()
     }
 
+    /// Sets the minimum time, that has to pass between two requests of this
+    /// session. If a command is send before that, `send_command` will sleep
+    /// until the interval has passed. This is `Duration::ZERO` by default.
+    /// Sending commands too quickly can get you throttled by the server, so
+    /// something around 1 second is recommended for bots, that send commands
+    /// in a loop
+    pub fn set_min_request_interval(&mut self, interval: Duration) {
+        self.min_request_interval = interval;
+    }
+
+    /// Returns the raw body of the last response, that the server send to
+    /// this session. This is cleared before every request, so it never
+    /// contains the response to an earlier request. If the response could not
+    /// be parsed, this will contain it, but if the request itself failed, or
+    /// the server responded with an error, this will be `None`. Include this
+    /// when reporting parsing errors
+    #[must_use]
+    pub fn last_response_raw(&self) -> Option<&str> {
+        self.last_response.as_deref()
+    }
+
+    async fn wait_for_request_interval(&mut self) {
+        if let Some(last) = self.last_request {
+            let remaining =
+                self.min_request_interval.saturating_sub(last.elapsed());
+            if !remaining.is_zero() {
+                tokio::time::sleep(remaining).await;
+            }
+        }
+        self.last_request = Some(Instant::now());
+    }
+
     /// Sends the command and updates the gamestate with the response from the
     /// server. A mutable reference to the gamestate will be returned. If an
     /// error is encountered, the gamestate is cleared and the error will be
@@ -630,13 +673,18 @@ impl SimpleSession {
         &mut self,
         cmd: T,
     ) -> Result<&mut GameState, SFError> {
+        self.last_response = None;
         if self.gamestate.is_none() {
+            self.wait_for_request_interval().await;
             let resp = self.session.login().await?;
+            self.last_response = Some(resp.raw_response().to_string());
             let gs = GameState::new(resp)?;
             self.gamestate = Some(gs);
             Self::short_sleep().await;
         }
 
+        self.wait_for_request_interval().await;
+        self.last_response = None;
         let resp = match self.session.send_command(cmd).await {
             Ok(resp) => resp,
             Err(err) => {
@@ -644,6 +692,7 @@ impl SimpleSession {
                 return Err(err);
             }
         };
+        self.last_response = Some(resp.raw_response().to_string());
 
         if let Some(gs) = &mut self.gamestate {
             if let Err(e) = gs.update(resp) {
@@ -654,4 +703,125 @@ impl SimpleSession {
 
         Ok(self.gamestate.as_mut().unwrap())
     }
+
+    /// Fetches all the given pages of the chosen Hall of Fame and collects
+    /// the entries on them in the list for that `kind`. The entries are moved
+    /// out of the `hall_of_fames` of the gamestate. Between each request,
+    /// this will wait for `delay`, so that the server is not spammed with
+    /// requests. A page with less than 51 entries is treated as the end of the
+    /// Hall of Fame. Note that a "broken" player (see
+    /// `Command::HallOfFamePage`) can also shorten a page, which will end the
+    /// crawl early
+    ///
+    /// # Errors
+    /// Have a look at `send_command` for a full list of possible errors
+    pub async fn crawl_hall_of_fame(
+        &mut self,
+        kind: HofKind,
+        pages: Range<usize>,
+        delay: Duration,
+    ) -> Result<HallOfFames, SFError> {
+        let mut res = HallOfFames::default();
+        let mut pages = pages.peekable();
+        while let Some(page) = pages.next() {
+            let gs = self.send_command(kind.page_command(page)).await?;
+            let fetched = take_hof_page(&mut res, &mut gs.hall_of_fames, kind);
+            if fetched < 51 {
+                if fetched > 0 {
+                    warn!("Hall of Fame page {page} only contained {fetched}");
+                }
+                break;
+            }
+            if pages.peek().is_some() {
+                tokio::time::sleep(delay).await;
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// Moves the entries of the given kind from the fetched `page` to the ones in
+/// `res` and copies the total amount of entries. Moving them makes sure, that
+/// a response without entries can not add the previous page a second time.
+/// Returns the amount of entries, that have been moved
+fn take_hof_page(
+    res: &mut HallOfFames,
+    page: &mut HallOfFames,
+    kind: HofKind,
+) -> usize {
+    fn take<T>(res: &mut Vec<T>, page: &mut Vec<T>) -> usize {
+        let len = page.len();
+        res.append(page);
+        len
+    }
+    match kind {
+        HofKind::Players => {
+            res.players_total = page.players_total;
+            take(&mut res.players, &mut page.players)
+        }
+        HofKind::Guilds => {
+            res.guilds_total = page.guilds_total;
+            take(&mut res.guilds, &mut page.guilds)
+        }
+        HofKind::Fortresses => {
+            res.fortresses_total = page.fortresses_total;
+            take(&mut res.fortresses, &mut page.fortresses)
+        }
+        HofKind::Pets => {
+            res.pets_total = page.pets_total;
+            take(&mut res.pets, &mut page.pets)
+        }
+        HofKind::Underworlds => {
+            res.underworlds_total = page.underworlds_total;
+            take(&mut res.underworlds, &mut page.underworlds)
+        }
+        HofKind::Hellevator => {
+            res.hellevator_total = page.hellevator_total;
+            take(&mut res.hellevator, &mut page.hellevator)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use chrono::Local;
+
+    use super::*;
+
+    fn player_page(gs: &mut GameState, first_rank: u32) {
+        let mut players = String::new();
+        for rank in first_rank..first_rank + 51 {
+            let _ = write!(players, "{rank},player{rank},,100,1000,1,;");
+        }
+        let body = format!("Ranklistplayer:{players}&maxrank:5000");
+        let response = Response::parse(body, Local::now().naive_local());
+        assert!(response.and_then(|r| gs.update(r)).is_ok());
+    }
+
+    #[test]
+    fn crawled_pages_are_not_counted_twice() {
+        let mut gs = GameState::default();
+        let mut res = HallOfFames::default();
+
+        player_page(&mut gs, 1);
+        let fetched =
+            take_hof_page(&mut res, &mut gs.hall_of_fames, HofKind::Players);
+        assert_eq!(fetched, 51);
+
+        // A response without any entries does not add the last page again
+        let fetched =
+            take_hof_page(&mut res, &mut gs.hall_of_fames, HofKind::Players);
+        assert_eq!(fetched, 0);
+
+        player_page(&mut gs, 52);
+        let fetched =
+            take_hof_page(&mut res, &mut gs.hall_of_fames, HofKind::Players);
+        assert_eq!(fetched, 51);
+
+        let ranks: Vec<_> = res.players.iter().map(|p| p.rank).collect();
+        assert_eq!(ranks, (1..=102).collect::<Vec<_>>());
+        assert_eq!(res.players_total, 5000);
+    }
 }
@@ -4,6 +4,7 @@
     clippy::cast_precision_loss,
     clippy::cast_possible_truncation
 )]
+use chrono::Local;
 use enum_map::{Enum, EnumMap};
 use fastrand::Rng;
 use strum::{EnumIter, IntoEnumIterator};
@@ -11,7 +12,8 @@ use strum::{EnumIter, IntoEnumIterator};
 use crate::{
     command::AttributeType,
     gamestate::{
-        character::Class, dungeons::CompanionClass, items::*, GameState,
+        character::Class, dungeons::CompanionClass, items::*,
+        social::OtherPlayer, underworld::UnderworldBuildingType, GameState,
     },
     misc::EnumMapGet,
 };
@@ -20,6 +22,21 @@ pub mod constants;
 
 use BattleEvent as BE;
 
+/// The maximum amount of attacks a berserker can do in a single turn
+pub const BERSERKER_MAX_ATTACKS: u32 = 15;
+/// The chance, that a berserker attacks again after an attack, as long as the
+/// defender is still alive and `BERSERKER_MAX_ATTACKS` has not been reached
+pub const BERSERKER_CONTINUE_CHANCE: f64 = 0.5;
+
+/// The amount of attacks a berserker is expected to do in one turn, if the
+/// defender survives all of them
+#[must_use]
+pub fn expected_berserker_attacks() -> f64 {
+    (0..BERSERKER_MAX_ATTACKS)
+        .map(|a| BERSERKER_CONTINUE_CHANCE.powi(a as i32))
+        .sum()
+}
+
 #[derive(Debug, Clone)]
 pub struct UpgradeableFighter {
     is_companion: bool,
@@ -37,6 +54,9 @@ pub struct UpgradeableFighter {
     portal_hp_bonus: u32,
     /// The damage bonus in percent this player has from the guild demon portal
     portal_dmg_bonus: u32,
+    /// The level of the gladiator trainer in the underworld. This is only
+    /// known for the own character
+    gladiator_lvl: Option<u32>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -58,6 +78,10 @@ pub struct BattleFighter {
     pub portal_dmg_bonus: f64,
     pub rounds_in_battle: u32,
     pub class_effect: ClassEffect,
+    /// The level of the gladiator trainer in the underworld. Increases the
+    /// damage of critical hits against fighters with a lower level. This is
+    /// `None`, if the level is not known (monsters & other players)
+    pub gladiator_lvl: Option<u32>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -67,21 +91,90 @@ pub enum HarpQuality {
     Good,
 }
 
+/// The stance a paladin fights in. Paladins start in the initial stance and
+/// switch between the offensive and defensive stance during a battle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaladinStance {
+    Initial,
+    Offensive,
+    Defensive,
+}
+
+impl PaladinStance {
+    /// The multiplier for the damage the paladin does in this stance
+    #[must_use]
+    pub fn damage_multiplier(self) -> f64 {
+        match self {
+            PaladinStance::Initial => 1.0,
+            PaladinStance::Offensive => 1.25,
+            PaladinStance::Defensive => 0.75,
+        }
+    }
+
+    /// The chance (0.0-1.0) the paladin has to block an attack in this stance
+    #[must_use]
+    pub fn block_chance(self) -> f64 {
+        match self {
+            PaladinStance::Initial => 0.3,
+            PaladinStance::Offensive => 0.25,
+            PaladinStance::Defensive => 0.5,
+        }
+    }
+}
+
+/// Values used in the combat formulas, that may differ on some (private)
+/// servers. The default values are the ones used on the official servers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CombatConstants {
+    /// The multiplier for the unarmed damage of an assassins main hand
+    pub unarmed_assassin_weapon_multiplier: f64,
+    /// The multiplier for the unarmed damage of an assassins offhand
+    pub unarmed_assassin_offhand_multiplier: f64,
+    /// The multiplier for the unarmed damage of all other classes
+    pub unarmed_multiplier: f64,
+    /// Every `rage_divisor` turns, the damage of all attacks increases by the
+    /// base damage
+    pub rage_divisor: f64,
+}
+
+impl Default for CombatConstants {
+    fn default() -> Self {
+        Self {
+            unarmed_assassin_weapon_multiplier: 1.25,
+            unarmed_assassin_offhand_multiplier: 0.875,
+            unarmed_multiplier: 0.7,
+            rage_divisor: 6.0,
+        }
+    }
+}
+
+impl CombatConstants {
+    /// The damage multiplier all attacks in the given turn have
+    #[must_use]
+    pub fn rage_bonus(&self, turn: u32) -> f64 {
+        // TODO: Is this the correct formula
+        1.0 + (f64::from(turn.saturating_sub(1)) / self.rage_divisor)
+    }
+}
+
 // Modified, but mostly copied from:
 // https://github.com/HafisCZ/sf-tools/blob/521c2773098d62fe21ae687de2047c05f84813b7/js/sim/base.js#L746C4-L765C6
 fn calc_unarmed_base_dmg(
     slot: EquipmentSlot,
     level: u16,
     class: Class,
+    constants: &CombatConstants,
 ) -> (u32, u32) {
     if level <= 10 {
         return (1, 2);
     }
     let dmg_level = f64::from(level - 9);
     let multiplier = match class {
-        Class::Assassin if slot == EquipmentSlot::Weapon => 1.25,
-        Class::Assassin => 0.875,
-        _ => 0.7,
+        Class::Assassin if slot == EquipmentSlot::Weapon => {
+            constants.unarmed_assassin_weapon_multiplier
+        }
+        Class::Assassin => constants.unarmed_assassin_offhand_multiplier,
+        _ => constants.unarmed_multiplier,
     };
 
     let base = dmg_level * multiplier * class.weapon_multiplier();
@@ -109,6 +202,9 @@ pub enum ClassEffect {
     DemonHunter {
         revived: u8,
     },
+    Paladin {
+        stance: PaladinStance,
+    },
     Normal,
 }
 
@@ -121,22 +217,55 @@ pub enum AttackType {
 }
 
 impl ClassEffect {
+    /// The effect a fighter of the given class has at the start of a battle.
+    /// Demon hunters need to track their revives and paladins their stance
+    /// from the start, everyone else starts without any effect
+    #[must_use]
+    pub fn initial(class: Class) -> ClassEffect {
+        match class {
+            Class::DemonHunter => ClassEffect::DemonHunter { revived: 0 },
+            Class::Paladin => ClassEffect::Paladin {
+                stance: PaladinStance::Initial,
+            },
+            _ => ClassEffect::Normal,
+        }
+    }
+
     fn druid_swoops(self) -> u8 {
         match self {
             ClassEffect::Druid { swoops, .. } => swoops,
             _ => 0,
         }
     }
+
+    /// The chance (0.0-1.0) a druid has to do a swoop attack. This starts at
+    /// 15% and grows by 5% with every swoop, up to a maximum of 50%. For
+    /// anything other than a druid, this is just the starting chance
+    #[must_use]
+    pub fn druid_swoop_chance(self) -> f64 {
+        0.15 + ((f64::from(self.druid_swoops().min(7)) * 5.0) / 100.0)
+    }
 }
 
 impl BattleFighter {
     #[must_use]
     pub fn from_monster(monster: &Monster) -> Self {
+        Self::from_monster_with_constants(monster, &CombatConstants::default())
+    }
+
+    /// Creates a fighter from the monster, but uses the provided constants
+    /// instead of the default ones to calculate its stats
+    #[must_use]
+    pub fn from_monster_with_constants(
+        monster: &Monster,
+        constants: &CombatConstants,
+    ) -> Self {
         // TODO: I assume this is unarmed damage, but I should check
         let weapon = calc_unarmed_base_dmg(
             EquipmentSlot::Weapon,
             monster.level,
             monster.class,
+            constants,
         );
 
         Self {
@@ -157,12 +286,23 @@ impl BattleFighter {
             },
             portal_dmg_bonus: 1.0,
             rounds_in_battle: 0,
-            class_effect: ClassEffect::Normal,
+            class_effect: ClassEffect::initial(monster.class),
+            gladiator_lvl: None,
         }
     }
 
     #[must_use]
     pub fn from_upgradeable(char: &UpgradeableFighter) -> Self {
+        Self::from_upgradeable_with_constants(char, &CombatConstants::default())
+    }
+
+    /// Creates a fighter from the upgradeable fighter, but uses the provided
+    /// constants instead of the default ones to calculate its stats
+    #[must_use]
+    pub fn from_upgradeable_with_constants(
+        char: &UpgradeableFighter,
+        constants: &CombatConstants,
+    ) -> Self {
         let attributes = char.attributes();
         let hp = char.hit_points(&attributes);
 
@@ -180,12 +320,14 @@ impl BattleFighter {
             let Some(item) = item else {
                 match slot {
                     EquipmentSlot::Weapon => {
-                        equip.weapon =
-                            calc_unarmed_base_dmg(slot, char.level, char.class);
+                        equip.weapon = calc_unarmed_base_dmg(
+                            slot, char.level, char.class, constants,
+                        );
                     }
                     EquipmentSlot::Shield if char.class == Class::Assassin => {
-                        equip.offhand =
-                            calc_unarmed_base_dmg(slot, char.level, char.class);
+                        equip.offhand = calc_unarmed_base_dmg(
+                            slot, char.level, char.class, constants,
+                        );
                     }
                     _ => {}
                 }
@@ -251,12 +393,22 @@ impl BattleFighter {
             current_hp: hp,
             equip,
             rounds_in_battle: 0,
-            class_effect: ClassEffect::Normal,
+            class_effect: ClassEffect::initial(char.class),
             portal_dmg_bonus,
             level: char.level,
+            gladiator_lvl: char.gladiator_lvl,
         }
     }
 
+    /// Creates the fighters of another player, that has been looked up. The
+    /// companions of other players are not known, so this will only contain
+    /// the character itself
+    #[must_use]
+    pub fn from_other_player(other: &OtherPlayer) -> Vec<Self> {
+        let fighter = UpgradeableFighter::from_other(other);
+        vec![Self::from_upgradeable(&fighter)]
+    }
+
     #[must_use]
     pub fn from_squad(squad: &PlayerFighterSquad) -> Vec<Self> {
         let mut res = if let Some(comps) = &squad.companions {
@@ -272,11 +424,99 @@ impl BattleFighter {
         res
     }
 
+    /// Restores the fighter to the state it had before the battle. This
+    /// resets the hp, the rounds in battle and the class effect (druid
+    /// swoops, bard melodies, necromancer minions, demon hunter revives &
+    /// paladin stances)
     pub fn reset(&mut self) {
-        self.class_effect = ClassEffect::Normal;
+        self.class_effect = ClassEffect::initial(self.class);
         self.current_hp = self.max_hp;
         self.rounds_in_battle = 0;
     }
+
+    /// Checks if this fighter has the reaction boost enchantment (Shadow of
+    /// the cowboy), which makes them more likely to start a fight
+    #[must_use]
+    pub fn has_reaction_boost(&self) -> bool {
+        self.equip.reaction_boost
+    }
+
+    /// Calculates the chance (0.0-1.0) this fighter has to land a critical hit
+    /// on the defender. This is the same value, that is used when simulating
+    /// an attack, so it includes the bonus of the current class effect (druid
+    /// bear, necromancer hound)
+    #[must_use]
+    pub fn crit_chance_against(&self, defender: &BattleFighter) -> f64 {
+        let luck_mod = self.attributes.get(AttributeType::Luck) * 5;
+        let raw_crit_chance = f64::from(luck_mod) / f64::from(defender.level);
+        let mut crit_chance = raw_crit_chance.min(0.5);
+
+        match self.class_effect {
+            ClassEffect::Druid { bear: true, .. }
+            | ClassEffect::Necromancer {
+                typ: Minion::Hound, ..
+            } => {
+                crit_chance += 0.1;
+            }
+            _ => {}
+        }
+        crit_chance
+    }
+
+    /// Calculates the damage the opening comet of a battle mage does to the
+    /// defender. This is a fraction of the max hp of the battle mage, that
+    /// depends on the class of the defender, but never more than a third of
+    /// the max hp of the defender. Mages repel the comet, so they take no
+    /// damage
+    #[must_use]
+    pub fn comet_damage_against(&self, defender: &BattleFighter) -> i64 {
+        use Class::{
+            Assassin, Bard, BattleMage, Berserker, DemonHunter, Druid, Mage,
+            Necromancer, Paladin, Scout, Warrior,
+        };
+        let dmg = match defender.class {
+            Mage => return 0,
+            Bard => self.max_hp / 10,
+            Scout | Assassin | Berserker | Necromancer | DemonHunter => {
+                self.max_hp / 5
+            }
+            Warrior | BattleMage | Druid => self.max_hp / 4,
+            Paladin => (self.max_hp as f64 / (10.0 / 3.0)).trunc() as i64,
+        };
+        dmg.min(defender.max_hp / 3)
+    }
+
+    /// Calculates the factor, that the damage of a critical hit from this
+    /// fighter against the defender gets multiplied with. This includes the
+    /// sword of vengeance enchantment, the bonus of the current class effect
+    /// (druid bear, necromancer hound) and the difference in gladiator levels,
+    /// if both levels are known
+    #[must_use]
+    pub fn crit_damage_factor_against(&self, defender: &BattleFighter) -> f64 {
+        let mut crit_dmg_factor = 2.0;
+
+        match self.class_effect {
+            ClassEffect::Druid { bear: true, .. } => {
+                crit_dmg_factor += 2.0;
+            }
+            ClassEffect::Necromancer {
+                typ: Minion::Hound, ..
+            } => {
+                crit_dmg_factor += 0.5;
+            }
+            _ => {}
+        }
+
+        if self.equip.extra_crit_dmg {
+            crit_dmg_factor += 0.05;
+        }
+        if let (Some(own), Some(other)) =
+            (self.gladiator_lvl, defender.gladiator_lvl)
+        {
+            crit_dmg_factor += 0.11 * f64::from(own.saturating_sub(other));
+        }
+        crit_dmg_factor
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -296,6 +536,51 @@ pub struct EquipmentEffects {
     armor: u32,
 }
 
+impl EquipmentEffects {
+    /// The total armor of the equipment
+    #[must_use]
+    pub fn armor(&self) -> u32 {
+        self.armor
+    }
+
+    /// The (min, max) damage of the main weapon
+    #[must_use]
+    pub fn weapon(&self) -> (u32, u32) {
+        self.weapon
+    }
+
+    /// The (min, max) damage of the offhand weapon, or the block chance and 0
+    /// for shields
+    #[must_use]
+    pub fn offhand(&self) -> (u32, u32) {
+        self.offhand
+    }
+
+    /// The resistance against the given element
+    #[must_use]
+    pub fn element_resistance(&self, element: Element) -> f64 {
+        *self.element_res.get(element)
+    }
+
+    /// The bonus damage of the given element
+    #[must_use]
+    pub fn element_damage(&self, element: Element) -> f64 {
+        *self.element_dmg.get(element)
+    }
+
+    /// Checks if the shadow of the cowboy enchantment is active
+    #[must_use]
+    pub fn reaction_boost(&self) -> bool {
+        self.reaction_boost
+    }
+
+    /// Checks if the sword of vengeance enchantment is active
+    #[must_use]
+    pub fn extra_crit_dmg(&self) -> bool {
+        self.extra_crit_dmg
+    }
+}
+
 #[derive(Debug, Clone, Copy, Enum, EnumIter)]
 pub enum Element {
     Lightning,
@@ -320,6 +605,28 @@ impl<'a> BattleTeam<'_> {
         self.fighters.get_mut(self.current_fighter)
     }
 
+    /// All the fighters of this team, in the order they fight in
+    #[must_use]
+    pub fn fighters(&self) -> &[BattleFighter] {
+        self.fighters
+    }
+
+    /// The amount of fighters of this team, that have been defeated so far.
+    /// Fighters fight one after another, so these are always the first ones
+    #[must_use]
+    pub fn defeated(&self) -> usize {
+        self.current_fighter.min(self.fighters.len())
+    }
+
+    /// The fighters of this team, that have not yet been defeated. After a
+    /// battle, these are the fighters, that survived it
+    #[must_use]
+    pub fn survivors(&self) -> &[BattleFighter] {
+        self.fighters
+            .get(self.current_fighter..)
+            .unwrap_or_default()
+    }
+
     fn reset(&mut self) {
         self.current_fighter = 0;
         for fighter in self.fighters.iter_mut() {
@@ -341,12 +648,29 @@ pub struct Battle<'a> {
     pub left: BattleTeam<'a>,
     pub right: BattleTeam<'a>,
     pub rng: Rng,
+    /// The constants used for the combat formulas
+    pub constants: CombatConstants,
+    /// The side, that will always start the battle, regardless of the reaction
+    /// boost & rng
+    forced_starter: Option<BattleSide>,
 }
 
 impl<'a> Battle<'a> {
     pub fn new(
         left: &'a mut [BattleFighter],
         right: &'a mut [BattleFighter],
+    ) -> Self {
+        Self::new_with_constants(left, right, CombatConstants::default())
+    }
+
+    /// Creates a new battle, that uses the provided constants instead of the
+    /// default ones. Note that the constants, which are used to create a
+    /// fighter (unarmed damage), have to be passed into the `_with_constants`
+    /// constructors of `BattleFighter` instead
+    pub fn new_with_constants(
+        left: &'a mut [BattleFighter],
+        right: &'a mut [BattleFighter],
+        constants: CombatConstants,
     ) -> Self {
         Self {
             round: 0,
@@ -360,10 +684,20 @@ impl<'a> Battle<'a> {
                 fighters: right,
             },
             rng: fastrand::Rng::default(),
+            constants,
+            forced_starter: None,
         }
     }
 
-    /// Simulates a battle between the two sides. Returns the winning side.
+    /// Overrides the decision of which side starts the battle, which would
+    /// normally be based on the reaction boost of both sides and rng. This is
+    /// mainly useful to deterministically test the effect of who starts.
+    /// This persists across resets
+    pub fn forced_starter(&mut self, side: BattleSide) {
+        self.forced_starter = Some(side);
+    }
+
+    /// Simulates a battle between the two sides. Returns the winning side
     pub fn simulate(&mut self, logger: &mut impl BattleLogger) -> BattleSide {
         self.reset();
         loop {
@@ -373,6 +707,37 @@ impl<'a> Battle<'a> {
         }
     }
 
+    /// Returns the side, that has won the battle. If both sides still have
+    /// fighters left, the battle is not yet finished and this returns None
+    #[must_use]
+    pub fn winner(&self) -> Option<BattleSide> {
+        match (self.left.current(), self.right.current()) {
+            (Some(_), None) => Some(BattleSide::Left),
+            (None, _) => Some(BattleSide::Right),
+            (Some(_), Some(_)) => None,
+        }
+    }
+
+    /// Returns the fighters of the side, that has won the battle. These are
+    /// the same fighters, that have been passed into `new()` on that side.
+    /// If the battle has not yet finished, this returns None
+    #[must_use]
+    pub fn winner_fighters(&self) -> Option<&[BattleFighter]> {
+        Some(match self.winner()? {
+            BattleSide::Left => self.left.fighters,
+            BattleSide::Right => self.right.fighters,
+        })
+    }
+
+    /// The team fighting on the given side
+    #[must_use]
+    pub fn team(&self, side: BattleSide) -> &BattleTeam<'a> {
+        match side {
+            BattleSide::Left => &self.left,
+            BattleSide::Right => &self.right,
+        }
+    }
+
     pub fn reset(&mut self) {
         self.round = 0;
         self.left.reset();
@@ -422,12 +787,18 @@ impl<'a> Battle<'a> {
             }
         } else {
             // The battle has not yet started. Figure out who side starts
+            let rng = &mut self.rng;
             let attacking_side =
-                match (right.equip.reaction_boost, left.equip.reaction_boost) {
-                    (true, true) | (false, false) if self.rng.bool() => Right,
-                    (true, false) => Right,
-                    _ => Left,
-                };
+                self.forced_starter.unwrap_or_else(|| {
+                    match (
+                        right.equip.reaction_boost,
+                        left.equip.reaction_boost,
+                    ) {
+                        (true, true) | (false, false) if rng.bool() => Right,
+                        (true, false) => Right,
+                        _ => Left,
+                    }
+                });
             self.started = Some(attacking_side);
             attacking_side
         };
@@ -437,24 +808,45 @@ impl<'a> Battle<'a> {
             Right => (right, left),
         };
 
-        let turn = self.round;
+        let rage = self.constants.rage_bonus(self.round);
         let rng = &mut self.rng;
         match attacker.class {
             Paladin => {
-                // TODO: Actually implement stances and stuff
-                attack(attacker, defender, rng, Weapon, turn, logger);
+                if let ClassEffect::Paladin { stance } =
+                    &mut attacker.class_effect
+                {
+                    // Every turn, there is a chance to switch the stance
+                    if rng.bool() {
+                        *stance = match stance {
+                            PaladinStance::Initial if rng.bool() => {
+                                PaladinStance::Offensive
+                            }
+                            PaladinStance::Defensive => {
+                                PaladinStance::Offensive
+                            }
+                            _ => PaladinStance::Defensive,
+                        };
+                        let stance = *stance;
+                        logger.log(BE::PaladinStanceChanged(
+                            attacker, defender, stance,
+                        ));
+                    }
+                }
+                attack(attacker, defender, rng, Weapon, rage, logger);
             }
             Warrior | Scout | Mage | DemonHunter => {
-                attack(attacker, defender, rng, Weapon, turn, logger);
+                attack(attacker, defender, rng, Weapon, rage, logger);
             }
             Assassin => {
-                attack(attacker, defender, rng, Weapon, turn, logger);
-                attack(attacker, defender, rng, Offhand, turn, logger);
+                attack(attacker, defender, rng, Weapon, rage, logger);
+                attack(attacker, defender, rng, Offhand, rage, logger);
             }
             Berserker => {
-                for _ in 0..15 {
-                    attack(attacker, defender, rng, Weapon, turn, logger);
-                    if defender.current_hp <= 0 || rng.bool() {
+                for _ in 0..BERSERKER_MAX_ATTACKS {
+                    attack(attacker, defender, rng, Weapon, rage, logger);
+                    if defender.current_hp <= 0
+                        || rng.f64() >= BERSERKER_CONTINUE_CHANCE
+                    {
                         break;
                     }
                 }
@@ -464,23 +856,13 @@ impl<'a> Battle<'a> {
                     if defender.class == Mage {
                         logger.log(BE::CometRepelled(attacker, defender));
                     } else {
-                        let dmg = match defender.class {
-                            Mage => 0,
-                            Bard => attacker.max_hp / 10,
-                            Scout | Assassin | Berserker | Necromancer
-                            | DemonHunter => attacker.max_hp / 5,
-                            Warrior | BattleMage | Druid => attacker.max_hp / 4,
-                            Paladin => (attacker.max_hp as f64 / (10.0 / 3.0))
-                                .trunc()
-                                as i64,
-                        };
-                        let dmg = dmg.min(defender.max_hp / 3);
+                        let dmg = attacker.comet_damage_against(defender);
                         logger.log(BE::CometAttack(attacker, defender));
                         // TODO: Can you dodge this?
                         do_damage(attacker, defender, dmg, rng, logger);
                     }
                 }
-                attack(attacker, defender, rng, Weapon, turn, logger);
+                attack(attacker, defender, rng, Weapon, rage, logger);
             }
             Druid => {
                 // Check if we do a sweep attack
@@ -490,11 +872,11 @@ impl<'a> Battle<'a> {
                 ) {
                     let swoops = attacker.class_effect.druid_swoops();
                     let swoop_chance =
-                        0.15 + ((f32::from(swoops) * 5.0) / 100.0);
+                        attacker.class_effect.druid_swoop_chance();
                     if defender.class != Class::Mage
-                        && rng.f32() <= swoop_chance
+                        && rng.f64() <= swoop_chance
                     {
-                        attack(attacker, defender, rng, Swoop, turn, logger);
+                        attack(attacker, defender, rng, Swoop, rage, logger);
                         attacker.class_effect = ClassEffect::Druid {
                             bear: false,
                             // max 7 to limit chance to 50%
@@ -503,7 +885,7 @@ impl<'a> Battle<'a> {
                     }
                 }
 
-                attack(attacker, defender, rng, Weapon, turn, logger);
+                attack(attacker, defender, rng, Weapon, rage, logger);
                 // TODO: Does this reset here, or on the start of the next
                 // attack?
                 attacker.class_effect = ClassEffect::Druid {
@@ -524,7 +906,7 @@ impl<'a> Battle<'a> {
                         ClassEffect::Bard { quality, remaining };
                     logger.log(BE::BardPlay(attacker, defender, quality));
                 }
-                attack(attacker, defender, rng, Weapon, turn, logger);
+                attack(attacker, defender, rng, Weapon, rage, logger);
                 if let ClassEffect::Bard { remaining, .. } =
                     &mut attacker.class_effect
                 {
@@ -552,7 +934,7 @@ impl<'a> Battle<'a> {
                         defender,
                         rng,
                         AttackType::Minion,
-                        turn,
+                        rage,
                         logger,
                     );
                 } else {
@@ -562,11 +944,11 @@ impl<'a> Battle<'a> {
                             defender,
                             rng,
                             AttackType::Minion,
-                            turn,
+                            rage,
                             logger,
                         );
                     }
-                    attack(attacker, defender, rng, Weapon, turn, logger);
+                    attack(attacker, defender, rng, Weapon, rage, logger);
                 }
                 if let ClassEffect::Necromancer { remaining, typ } =
                     &mut attacker.class_effect
@@ -649,7 +1031,7 @@ fn attack(
     defender: &mut BattleFighter,
     rng: &mut Rng,
     typ: AttackType,
-    turn: u32,
+    rage_bonus: f64,
     logger: &mut impl BattleLogger,
 ) {
     if defender.current_hp <= 0 {
@@ -685,6 +1067,12 @@ fn attack(
             logger.log(BE::Blocked(attacker, defender));
             return;
         }
+        if let ClassEffect::Paladin { stance } = defender.class_effect {
+            if rng.f64() < stance.block_chance() {
+                logger.log(BE::Blocked(attacker, defender));
+                return;
+            }
+        }
     }
 
     // TODO: Most of this can be reused, as long as the opponent does not
@@ -696,10 +1084,16 @@ fn attack(
     let mut elemental_bonus = 1.0;
     for element in Element::iter() {
         let plus = attacker.equip.element_dmg.get(element);
-        let minus = defender.equip.element_dmg.get(element);
+        let minus = defender.equip.element_res.get(element);
 
         if plus > minus {
             elemental_bonus += plus - minus;
+            logger.log(BE::ElementalModifier(
+                attacker,
+                defender,
+                element,
+                plus - minus,
+            ));
         }
     }
 
@@ -730,12 +1124,10 @@ fn attack(
             Minion::Golem => 1.0,
         },
         ClassEffect::Druid { .. } if typ == AttackType::Swoop => 1.8,
+        ClassEffect::Paladin { stance } => stance.damage_multiplier(),
         _ => 1.0,
     };
 
-    // TODO: Is this the correct formula
-    let rage_bonus = 1.0 + (f64::from(turn.saturating_sub(1)) / 6.0);
-
     let damage_bonus = char_damage_modifier
         * attacker.portal_dmg_bonus
         * elemental_bonus
@@ -760,29 +1152,9 @@ fn attack(
 
     // Crits
 
-    let luck_mod = attacker.attributes.get(AttributeType::Luck) * 5;
-    let raw_crit_chance = f64::from(luck_mod) / f64::from(defender.level);
-    let mut crit_chance = raw_crit_chance.min(0.5);
-    let mut crit_dmg_factor = 2.0;
-
-    match attacker.class_effect {
-        ClassEffect::Druid { bear: true, .. } => {
-            crit_chance += 0.1;
-            crit_dmg_factor += 2.0;
-        }
-        ClassEffect::Necromancer {
-            typ: Minion::Hound, ..
-        } => {
-            crit_chance += 0.1;
-            crit_dmg_factor += 0.5;
-        }
-        _ => {}
-    }
-
+    let crit_chance = attacker.crit_chance_against(defender);
     if rng.f64() <= crit_chance {
-        if attacker.equip.extra_crit_dmg {
-            crit_dmg_factor += 0.05;
-        };
+        let crit_dmg_factor = attacker.crit_damage_factor_against(defender);
         logger.log(BE::Crit(attacker, defender));
         damage = (damage as f64 * crit_dmg_factor) as i64;
     }
@@ -830,7 +1202,14 @@ impl PlayerFighterSquad {
             .unwrap_or_default()
             .into();
 
+        let gladiator_lvl = gs
+            .underworld
+            .as_ref()
+            .map(|a| a.buildings.get(UnderworldBuildingType::GladiatorTrainer))
+            .map(|a| u32::from(a.level));
+
         let char = &gs.character;
+        let active_potions = char.effective_potions(Local::now());
         let character = UpgradeableFighter {
             is_companion: false,
             level: char.level,
@@ -838,20 +1217,17 @@ impl PlayerFighterSquad {
             attribute_basis: char.attribute_basis,
             _attributes_bought: char.attribute_times_bought,
             equipment: char.equipment.clone(),
-            active_potions: char.active_potions,
+            active_potions,
             pet_attribute_bonus_perc,
             portal_hp_bonus,
             portal_dmg_bonus,
+            gladiator_lvl,
         };
         let mut companions = None;
         if let Some(comps) = &gs.dungeons.companions {
-            let classes = [
-                CompanionClass::Warrior,
-                CompanionClass::Mage,
-                CompanionClass::Scout,
-            ];
-
-            let res = classes.map(|class| {
+            // Building the map from the keys makes sure, that every companion
+            // ends up at the entry of its own class
+            let res = EnumMap::from_fn(|class: CompanionClass| {
                 let comp = comps.get(class);
                 UpgradeableFighter {
                     is_companion: true,
@@ -860,13 +1236,17 @@ impl PlayerFighterSquad {
                     attribute_basis: comp.attributes,
                     _attributes_bought: EnumMap::default(),
                     equipment: comp.equipment.clone(),
-                    active_potions: char.active_potions,
+                    active_potions,
                     pet_attribute_bonus_perc,
                     portal_hp_bonus,
                     portal_dmg_bonus,
+                    gladiator_lvl,
                 }
             });
-            companions = Some(EnumMap::from_array(res));
+            debug_assert!(res
+                .iter()
+                .all(|(class, comp)| comp.class == Class::from(class)));
+            companions = Some(res);
         }
 
         PlayerFighterSquad {
@@ -874,44 +1254,45 @@ impl PlayerFighterSquad {
             companions,
         }
     }
+
+    /// The fighter of the companion with the given class, if the character
+    /// has unlocked the companions
+    #[must_use]
+    pub fn companion(
+        &self,
+        class: CompanionClass,
+    ) -> Option<&UpgradeableFighter> {
+        Some(self.companions.as_ref()?.get(class))
+    }
 }
 
 impl UpgradeableFighter {
+    /// Creates a fighter from another player, that has been looked up. The
+    /// level of the gladiator trainer is not known for other players, so it
+    /// will not be taken into account in fights
     #[must_use]
-    pub fn attributes(&self) -> EnumMap<AttributeType, u32> {
-        let mut total = EnumMap::default();
-
-        for equip in self.equipment.0.iter().flat_map(|a| a.1) {
-            for (k, v) in &equip.attributes {
-                *total.get_mut(k) += v;
-            }
-
-            if let Some(GemSlot::Filled(gem)) = &equip.gem_slot {
-                use AttributeType as AT;
-                let mut value = gem.value;
-                if matches!(equip.typ, ItemType::Weapon { .. })
-                    && !self.is_companion
-                {
-                    value *= 2;
-                }
-
-                let mut add_atr = |at| *total.get_mut(at) += value;
-                match gem.typ {
-                    GemType::Strength => add_atr(AT::Strength),
-                    GemType::Dexterity => add_atr(AT::Dexterity),
-                    GemType::Intelligence => add_atr(AT::Intelligence),
-                    GemType::Constitution => add_atr(AT::Constitution),
-                    GemType::Luck => add_atr(AT::Luck),
-                    GemType::All => {
-                        total.iter_mut().for_each(|a| *a.1 += value);
-                    }
-                    GemType::Legendary => {
-                        add_atr(AT::Constitution);
-                        add_atr(self.class.main_attribute());
-                    }
-                }
-            }
+    pub fn from_other(other: &OtherPlayer) -> Self {
+        UpgradeableFighter {
+            is_companion: false,
+            level: other.level,
+            class: other.class,
+            attribute_basis: other.base_attributes,
+            _attributes_bought: EnumMap::default(),
+            pet_attribute_bonus_perc: other
+                .pet_attribute_bonus_perc
+                .map(|_, a| f64::from(a) / 100.0),
+            equipment: other.equipment.clone(),
+            active_potions: other.active_potions,
+            portal_hp_bonus: other.portal_hp_bonus,
+            portal_dmg_bonus: other.portal_dmg_bonus,
+            gladiator_lvl: None,
         }
+    }
+
+    #[must_use]
+    pub fn attributes(&self) -> EnumMap<AttributeType, u32> {
+        let mut total =
+            self.equipment.attributes(self.class, self.is_companion);
 
         let class_bonus: f64 = match self.class {
             Class::BattleMage => 0.1111,
@@ -960,7 +1341,7 @@ impl UpgradeableFighter {
             .active_potions
             .iter()
             .flatten()
-            .any(|a| a.typ == PotionType::EternalLife)
+            .any(|a| a.typ.is_life_potion())
         {
             total = (total as f64 * 1.25).trunc() as i64;
         }
@@ -993,6 +1374,7 @@ impl UpgradeableFighter {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct Monster {
     pub level: u16,
     pub class: Class,
@@ -1036,6 +1418,11 @@ pub enum BattleEvent<'a, 'b> {
     MinionSpawned(&'b BattleFighter, &'b BattleFighter, Minion),
     MinionSkeletonRevived(&'b BattleFighter, &'b BattleFighter),
     BardPlay(&'b BattleFighter, &'b BattleFighter, HarpQuality),
+    /// The paladin (first) has switched into a new stance
+    PaladinStanceChanged(&'b BattleFighter, &'b BattleFighter, PaladinStance),
+    /// The attack gets extra damage from an element. The value is the bonus
+    /// (0.0-1.0), that gets added to the damage multiplier
+    ElementalModifier(&'b BattleFighter, &'b BattleFighter, Element, f64),
     FighterDefeat(&'a Battle<'b>, BattleSide),
 }
 
@@ -1047,3 +1434,408 @@ impl BattleLogger for () {
     fn log(&mut self, _event: BattleEvent<'_, '_>) {
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamestate::{dungeons::Companion, underworld::Underworld};
+
+    fn test_monster() -> Monster {
+        Monster::new(100, Class::Warrior, [500, 200, 200, 800, 300], 80_000, 0)
+    }
+
+    #[test]
+    fn own_squad_has_gladiator_level() {
+        let mut gs = GameState::default();
+        let mut underworld = Underworld::default();
+        underworld
+            .buildings
+            .get_mut(UnderworldBuildingType::GladiatorTrainer)
+            .level = 7;
+        gs.underworld = Some(underworld);
+
+        let squad = PlayerFighterSquad::new(&gs);
+        assert_eq!(squad.character.gladiator_lvl, Some(7));
+        let fighter = BattleFighter::from_upgradeable(&squad.character);
+        assert_eq!(fighter.gladiator_lvl, Some(7));
+    }
+
+    #[test]
+    fn gladiator_bonus_needs_both_levels() {
+        let monster = BattleFighter::from_monster(&test_monster());
+        let base = monster.crit_damage_factor_against(&monster);
+
+        let mut own = monster.clone();
+        own.gladiator_lvl = Some(5);
+        // The level of the monster is unknown, so no bonus is applied
+        assert!((own.crit_damage_factor_against(&monster) - base).abs() < 1e-9);
+
+        let mut other = monster.clone();
+        other.gladiator_lvl = Some(2);
+        let factor = own.crit_damage_factor_against(&other);
+        assert!((factor - base - 0.33).abs() < 1e-9);
+        // A higher level on the defender does not reduce the damage
+        assert!((other.crit_damage_factor_against(&own) - base).abs() < 1e-9);
+    }
+
+    #[test]
+    fn crit_chance_matches_formula() {
+        let defender = BattleFighter::from_monster(&test_monster());
+        let mut attacker = defender.clone();
+        // 6 luck * 5 / 100 level
+        *attacker.attributes.get_mut(AttributeType::Luck) = 6;
+        assert!((attacker.crit_chance_against(&defender) - 0.3).abs() < 1e-9);
+
+        attacker.class_effect = ClassEffect::Druid {
+            bear: true,
+            swoops: 0,
+        };
+        assert!((attacker.crit_chance_against(&defender) - 0.4).abs() < 1e-9);
+
+        // The base chance is capped at 50%, but the bear bonus is not
+        *attacker.attributes.get_mut(AttributeType::Luck) = 100_000;
+        assert!((attacker.crit_chance_against(&defender) - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn comet_damage_per_class() {
+        let mut battle_mage = BattleFighter::from_monster(&test_monster());
+        battle_mage.class = Class::BattleMage;
+        battle_mage.max_hp = 10_000;
+
+        let expected = [
+            (Class::Warrior, 2_500),
+            (Class::Mage, 0),
+            (Class::Scout, 2_000),
+            (Class::Assassin, 2_000),
+            (Class::BattleMage, 2_500),
+            (Class::Berserker, 2_000),
+            (Class::DemonHunter, 2_000),
+            (Class::Druid, 2_500),
+            (Class::Bard, 1_000),
+            (Class::Necromancer, 2_000),
+            (Class::Paladin, 3_000),
+        ];
+        assert_eq!(expected.len(), Class::iter().count());
+        for (class, dmg) in expected {
+            let mut defender = BattleFighter::from_monster(&test_monster());
+            defender.class = class;
+            defender.max_hp = 1_000_000;
+            assert_eq!(
+                battle_mage.comet_damage_against(&defender),
+                dmg,
+                "{class:?}"
+            );
+            // The comet never does more than a third of the defenders max hp
+            defender.max_hp = 3_000;
+            assert_eq!(
+                battle_mage.comet_damage_against(&defender),
+                dmg.min(1_000),
+                "{class:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn runes_are_not_capped_in_fights() {
+        let mut gs = GameState::default();
+        let mut hat = Item::new(ItemType::Hat);
+        hat.rune = Some(Rune {
+            typ: RuneType::FireResistance,
+            value: MAX_RUNE_RESISTANCE + 10,
+        });
+        *gs.character.equipment.0.get_mut(EquipmentSlot::Hat) = Some(hat);
+
+        let squad = PlayerFighterSquad::new(&gs);
+        let fighter = BattleFighter::from_upgradeable(&squad.character);
+        let fire_res = *fighter.equip.element_res.get(Element::Fire);
+        assert!((fire_res - 0.8).abs() < 1e-9);
+        let cold_res = *fighter.equip.element_res.get(Element::Cold);
+        assert!(cold_res.abs() < 1e-9);
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        damage: Vec<i64>,
+        elemental: Vec<(Element, f64)>,
+        blocked: usize,
+        attacks: usize,
+    }
+
+    impl BattleLogger for Recorder {
+        fn log(&mut self, event: BattleEvent<'_, '_>) {
+            match event {
+                BattleEvent::DamageReceived(_, _, dmg) => {
+                    self.damage.push(dmg);
+                }
+                BattleEvent::ElementalModifier(_, _, element, val) => {
+                    self.elemental.push((element, val));
+                }
+                BattleEvent::Blocked(..) => self.blocked += 1,
+                BattleEvent::Attack(..) => self.attacks += 1,
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_simulations_are_identical() {
+        let mut left = vec![
+            BattleFighter::from_monster(&Monster::new(
+                100,
+                Class::Druid,
+                [200, 200, 500, 800, 300],
+                80_000,
+                0,
+            )),
+            BattleFighter::from_monster(&test_monster()),
+        ];
+        let mut right = vec![BattleFighter::from_monster(&Monster::new(
+            100,
+            Class::Necromancer,
+            [200, 200, 500, 800, 300],
+            120_000,
+            0,
+        ))];
+        let mut battle = Battle::new(&mut left, &mut right);
+
+        let run = |battle: &mut Battle<'_>| {
+            battle.rng = Rng::with_seed(1234);
+            let mut recorder = Recorder::default();
+            let winner = battle.simulate(&mut recorder);
+            (winner, battle.round, recorder.damage)
+        };
+        let first = run(&mut battle);
+        let second = run(&mut battle);
+        assert!(!first.2.is_empty());
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn elemental_modifier_subtracts_defender_resistance() {
+        let mut attacker = BattleFighter::from_monster(&test_monster());
+        *attacker.equip.element_dmg.get_mut(Element::Fire) = 0.4;
+        let mut defender = BattleFighter::from_monster(&test_monster());
+        *defender.equip.element_res.get_mut(Element::Fire) = 0.1;
+
+        let mut left = vec![attacker.clone()];
+        let mut right = vec![defender.clone()];
+        let mut battle = Battle::new(&mut left, &mut right);
+        let mut recorder = Recorder::default();
+        battle.simulate(&mut recorder);
+        assert!(!recorder.elemental.is_empty());
+        for (element, val) in recorder.elemental {
+            assert!(matches!(element, Element::Fire));
+            assert!((val - 0.3).abs() < 1e-9);
+        }
+
+        // The resistance fully negates the bonus damage
+        *defender.equip.element_res.get_mut(Element::Fire) = 0.5;
+        let mut left = vec![attacker];
+        let mut right = vec![defender];
+        let mut battle = Battle::new(&mut left, &mut right);
+        let mut recorder = Recorder::default();
+        battle.simulate(&mut recorder);
+        assert!(recorder.elemental.is_empty());
+    }
+
+    fn paladin(stance: PaladinStance) -> BattleFighter {
+        let mut paladin = BattleFighter::from_monster(&Monster::new(
+            100,
+            Class::Paladin,
+            [500, 200, 200, 800, 0],
+            80_000,
+            0,
+        ));
+        paladin.class_effect = ClassEffect::Paladin { stance };
+        paladin
+    }
+
+    #[test]
+    fn paladin_stance_changes_damage() {
+        let mut defender = BattleFighter::from_monster(&test_monster());
+        defender.current_hp = i64::MAX;
+        let mut rng = Rng::with_seed(42);
+
+        let mut damage = |stance| {
+            let mut attacker = paladin(stance);
+            *attacker.attributes.get_mut(AttributeType::Luck) = 0;
+            attacker.equip.weapon = (100, 100);
+            let mut recorder = Recorder::default();
+            attack(
+                &mut attacker,
+                &mut defender,
+                &mut rng,
+                AttackType::Weapon,
+                1.0,
+                &mut recorder,
+            );
+            recorder.damage.first().copied().unwrap_or_default() as f64
+        };
+        let initial = damage(PaladinStance::Initial);
+        assert!(initial > 0.0);
+        let offensive = damage(PaladinStance::Offensive);
+        assert!((offensive / initial - 1.25).abs() < 0.01);
+        let defensive = damage(PaladinStance::Defensive);
+        assert!((defensive / initial - 0.75).abs() < 0.01);
+    }
+
+    #[test]
+    fn paladin_stance_changes_block_chance() {
+        let mut rng = Rng::with_seed(42);
+        let mut blocked = |stance| {
+            let mut attacker = BattleFighter::from_monster(&test_monster());
+            let mut defender = paladin(stance);
+            defender.current_hp = i64::MAX;
+            let mut recorder = Recorder::default();
+            for _ in 0..2000 {
+                attack(
+                    &mut attacker,
+                    &mut defender,
+                    &mut rng,
+                    AttackType::Weapon,
+                    1.0,
+                    &mut recorder,
+                );
+            }
+            recorder.blocked
+        };
+        assert!((400..600).contains(&blocked(PaladinStance::Offensive)));
+        assert!((900..1100).contains(&blocked(PaladinStance::Defensive)));
+    }
+
+    #[test]
+    fn paladins_start_in_the_initial_stance() {
+        let mut fighter = paladin(PaladinStance::Defensive);
+        fighter.reset();
+        assert_eq!(
+            fighter.class_effect,
+            ClassEffect::Paladin {
+                stance: PaladinStance::Initial
+            }
+        );
+    }
+
+    fn berserker_turn_attacks(seed: u64, defender_hp: i64) -> usize {
+        let mut berserker = BattleFighter::from_monster(&test_monster());
+        berserker.class = Class::Berserker;
+        let mut defender = BattleFighter::from_monster(&test_monster());
+        defender.max_hp = defender_hp;
+        defender.current_hp = defender_hp;
+
+        let mut left = [berserker];
+        let mut right = [defender];
+        let mut battle = Battle::new(&mut left, &mut right);
+        battle.forced_starter(BattleSide::Left);
+        battle.rng = Rng::with_seed(seed);
+        let mut recorder = Recorder::default();
+        battle.simulate_turn(&mut recorder);
+        recorder.attacks
+    }
+
+    #[test]
+    fn berserker_attacks_stop_at_the_cap() {
+        let max = BERSERKER_MAX_ATTACKS as usize;
+        let attacks: Vec<_> = (0..100_000)
+            .map(|seed| berserker_turn_attacks(seed, i64::MAX))
+            .collect();
+        assert!(attacks.iter().all(|a| (1..=max).contains(a)));
+        // The chance to reach the cap is 0.5^14, so some seeds must hit it
+        assert!(attacks.contains(&max));
+        assert!(attacks.contains(&1));
+    }
+
+    #[test]
+    fn berserker_attacks_stop_when_the_defender_dies() {
+        for seed in 0..1_000 {
+            assert_eq!(berserker_turn_attacks(seed, 1), 1);
+        }
+    }
+
+    #[test]
+    fn three_vs_three_battle() {
+        let strong = BattleFighter::from_monster(&Monster::new(
+            200,
+            Class::Warrior,
+            [3000, 500, 500, 3000, 800],
+            2_000_000,
+            0,
+        ));
+        let weak = BattleFighter::from_monster(&Monster::new(
+            50,
+            Class::Mage,
+            [100, 100, 300, 200, 100],
+            10_000,
+            0,
+        ));
+        let mut left = vec![strong.clone(), strong.clone(), strong];
+        let mut right = vec![weak.clone(), weak.clone(), weak];
+        let mut battle = Battle::new(&mut left, &mut right);
+
+        let run = |battle: &mut Battle<'_>| {
+            battle.rng = Rng::with_seed(99);
+            let winner = battle.simulate(&mut ());
+            let left = battle.team(BattleSide::Left);
+            (
+                winner,
+                battle.round,
+                left.defeated(),
+                left.survivors().len(),
+            )
+        };
+        let first = run(&mut battle);
+        assert_eq!(first.0, BattleSide::Left);
+        assert_eq!(battle.winner(), Some(BattleSide::Left));
+        assert_eq!(battle.winner_fighters().map(<[_]>::len), Some(3));
+        assert_eq!(first.2 + first.3, 3);
+        assert!(first.3 > 0);
+
+        let right = battle.team(BattleSide::Right);
+        assert_eq!(right.defeated(), 3);
+        assert!(right.survivors().is_empty());
+        assert_eq!(right.fighters().len(), 3);
+
+        // The same seed always leads to the same battle
+        assert_eq!(run(&mut battle), first);
+    }
+
+    #[test]
+    fn companions_are_built_for_their_class() {
+        let mut gs = GameState::default();
+        gs.character.class = Class::Mage;
+        let squad = PlayerFighterSquad::new(&gs);
+        assert!(squad.companion(CompanionClass::Warrior).is_none());
+        assert_eq!(BattleFighter::from_squad(&squad).len(), 1);
+
+        gs.dungeons.companions = Some(EnumMap::from_fn(|class| {
+            let level = match class {
+                CompanionClass::Warrior => 10,
+                CompanionClass::Mage => 20,
+                CompanionClass::Scout => 30,
+            };
+            Companion {
+                level,
+                ..Default::default()
+            }
+        }));
+        let squad = PlayerFighterSquad::new(&gs);
+        for (class, level) in [
+            (CompanionClass::Warrior, 10),
+            (CompanionClass::Mage, 20),
+            (CompanionClass::Scout, 30),
+        ] {
+            let companion = squad.companion(class);
+            assert!(companion.is_some_and(|c| c.class == Class::from(class)
+                && c.level == level
+                && c.is_companion));
+        }
+
+        // The companions fight before the character
+        let fighters = BattleFighter::from_squad(&squad);
+        let classes: Vec<_> = fighters.iter().map(|a| a.class).collect();
+        assert_eq!(
+            classes,
+            [Class::Warrior, Class::Mage, Class::Scout, Class::Mage]
+        );
+    }
+}